@@ -0,0 +1,76 @@
+//! Benchmarks of the conversions in `ndi_sdk::convert`, over full frames at 1080p and 4K
+//!
+//! Run with `cargo bench`. The throughput is the size of the input frame, so formats can be compared per frame.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ndi_sdk::convert;
+use ndi_sdk::receive::FourCCType;
+
+const SIZES: [(&str, usize, usize); 2] = [("1080p", 1920, 1080), ("4k", 3840, 2160)];
+
+/// A frame of the given format with a tightly packed stride, filled with a repeating pattern
+fn frame(four_cc: FourCCType, width: usize, height: usize) -> (Vec<u8>, usize) {
+    let stride = match four_cc {
+        FourCCType::UYVY | FourCCType::UYVA => width * 2,
+        FourCCType::NV12 | FourCCType::YV12 | FourCCType::I420 => width,
+        _ => width * 4,
+    };
+    let len = convert::frame_data_len(four_cc, width, height, stride);
+    let data = (0..len).map(|i| (i % 251) as u8).collect();
+    (data, stride)
+}
+
+fn to_rgba(c: &mut Criterion) {
+    let formats = [
+        FourCCType::BGRA,
+        FourCCType::RGBX,
+        FourCCType::UYVY,
+        FourCCType::UYVA,
+        FourCCType::NV12,
+        FourCCType::I420,
+    ];
+
+    let mut group = c.benchmark_group("to_rgba_into");
+    for (name, width, height) in SIZES.iter() {
+        let mut out = vec![0; width * height * 4];
+        for four_cc in formats.iter() {
+            let (data, stride) = frame(*four_cc, *width, *height);
+            group.throughput(Throughput::Bytes(data.len() as u64));
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", four_cc), name), &data, |b, data| {
+                b.iter(|| convert::to_rgba_into(black_box(data), *four_cc, *width, *height, stride, &mut out))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn swizzle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bgra_to_rgba_in_place");
+    for (name, width, height) in SIZES.iter() {
+        let (mut data, _) = frame(FourCCType::BGRA, *width, *height);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(*name, |b| {
+            b.iter(|| convert::bgra_to_rgba_in_place(black_box(&mut data)))
+        });
+    }
+    group.finish();
+}
+
+fn to_i420(c: &mut Criterion) {
+    let formats = [FourCCType::UYVY, FourCCType::NV12, FourCCType::I420];
+
+    let mut group = c.benchmark_group("to_i420");
+    for (name, width, height) in SIZES.iter() {
+        for four_cc in formats.iter() {
+            let (data, stride) = frame(*four_cc, *width, *height);
+            group.throughput(Throughput::Bytes(data.len() as u64));
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", four_cc), name), &data, |b, data| {
+                b.iter(|| convert::to_i420(black_box(data), *four_cc, *width, *height, stride))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, to_rgba, swizzle, to_i420);
+criterion_main!(benches);
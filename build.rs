@@ -1,33 +1,41 @@
-#[allow(unused_imports)]
 use std::io::ErrorKind;
-use std::path::{PathBuf, Path};
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
-#[cfg(target_os = "macos")]
 fn main() {
-    // Base path to the NDI SDK from the environment variable
-    let ndi_sdk_path = env::var("NDI_SDK_DIR").expect("NDI_SDK_DIR environment variable not set");
-
-    // Paths to the include and main header file
-    let ndi_include_path = format!("{}/include", ndi_sdk_path);
-    let main_header = format!("{}/Processing.NDI.Lib.h", ndi_include_path);
+    // The build script runs on the host, so the target has to be checked at runtime to support cross compiling
+    let target_os = env::var("CARGO_CFG_TARGET_OS").expect("CARGO_CFG_TARGET_OS environment variable not set");
+    match target_os.as_str() {
+        "macos" => build_macos(),
+        "windows" => build_windows(),
+        "linux" => build_linux(),
+        "android" => build_android(),
+        "ios" => build_ios(),
+        os => panic!("Unsupported target os: {}", os),
+    }
 
-    // Path to the library directory
-    let lib_path = format!("{}/lib/macOS", ndi_sdk_path);
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&target_os);
+}
 
-    // Inform cargo about the search path for the linker and the library to link against
-    println!("cargo:rustc-link-search=native={}", lib_path);
-    println!("cargo:rustc-link-lib=dylib=ndi");
+/// Generate bindings from the headers of the installed SDK, to use instead of the pregenerated ones in src/sdk.rs
+#[cfg(feature = "bindgen")]
+fn generate_bindings(target_os: &str) {
+    println!("cargo:rerun-if-env-changed=NDI_SDK_DIR");
+    let ndi_sdk_path =
+        env::var("NDI_SDK_DIR").expect("NDI_SDK_DIR environment variable must be set to generate bindings");
 
-    // Set rpath
-    println!("cargo:rustc-link-arg=-rpath");
-    println!("cargo:rustc-link-arg={}", lib_path);
+    // Paths to the include and main header file
+    let ndi_include_path = Path::new(&ndi_sdk_path).join(if target_os == "windows" { "Include" } else { "include" });
+    let main_header = ndi_include_path.join("Processing.NDI.Lib.h");
+    if !main_header.exists() {
+        panic!("{} not found", main_header.display());
+    }
 
-    // Generate the bindings
     let bindings = bindgen::Builder::default()
-        .header(main_header)
-        .clang_arg(format!("-I{}", ndi_include_path))
-        .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+        .header(main_header.to_string_lossy())
+        .clang_arg(format!("-I{}", ndi_include_path.display()))
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .generate()
         .expect("Unable to generate bindings");
 
@@ -38,12 +46,70 @@ fn main() {
         .expect("Couldn't write bindings!");
 }
 
+fn dynamic_link() -> bool {
+    env::var_os("CARGO_FEATURE_DYNAMIC_LINK").is_some()
+}
+
+fn build_macos() {
+    // Base path to the NDI SDK from the environment variable
+    println!("cargo:rerun-if-env-changed=NDI_SDK_DIR");
+    let ndi_sdk_path = env::var("NDI_SDK_DIR").ok();
+
+    // Newer SDKs have a folder per architecture, older ones a universal library. The redistributable installs to
+    // /usr/local/lib
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").expect("CARGO_CFG_TARGET_ARCH environment variable not set");
+    let arch_dir = match target_arch.as_str() {
+        "aarch64" => "arm64",
+        arch => arch,
+    };
+    let mut lib_dirs = Vec::new();
+    if let Some(sdk_path) = &ndi_sdk_path {
+        let sdk_lib = Path::new(sdk_path).join("lib").join("macOS");
+        lib_dirs.push(sdk_lib.join(arch_dir));
+        lib_dirs.push(sdk_lib);
+    }
+    lib_dirs.push(PathBuf::from("/usr/local/lib"));
+
+    let lib_path = match lib_dirs.iter().find(|dir| dir.join("libndi.dylib").exists()) {
+        Some(dir) => dir,
+        None if dynamic_link() => return,
+        None => panic!(
+            "libndi.dylib not found, searched: {}",
+            lib_dirs
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    if !dynamic_link() {
+        // Inform cargo about the search path for the linker and the library to link against
+        println!("cargo:rustc-link-search=native={}", lib_path.display());
+        println!("cargo:rustc-link-lib=dylib=ndi");
+    }
+
+    // Set rpath
+    println!("cargo:rustc-link-arg=-rpath");
+    println!("cargo:rustc-link-arg={}", lib_path.display());
+}
+
+/// The environment variables set by the NDI runtime installers, newest first
+const RUNTIME_DIR_VARS: [&str; 4] = [
+    "NDI_RUNTIME_DIR_V6",
+    "NDI_RUNTIME_DIR_V5",
+    "NDI_RUNTIME_DIR_V4",
+    "NDI_RUNTIME_DIR_V3",
+];
 
 fn choose_source_dir() -> Option<String> {
     // Follow the 'recommended' install path
-    if let Ok(path) = env::var("NDI_RUNTIME_DIR_V3") {
-        if std::path::Path::new(&path).exists() {
-            return Some(path);
+    for var in RUNTIME_DIR_VARS.iter() {
+        println!("cargo:rerun-if-env-changed={}", var);
+        if let Ok(path) = env::var(var) {
+            if std::path::Path::new(&path).exists() {
+                return Some(path);
+            }
         }
     }
 
@@ -57,19 +123,13 @@ fn choose_source_dir() -> Option<String> {
     None
 }
 
-#[cfg(target_os = "windows")]
-fn main() {
+fn build_windows() {
     let source_dir = choose_source_dir();
 
-    // Copy the .dll/.lib files to the deps folder, to make it build
-    if let Some(path) = source_dir {
-        let source_path = Path::new(&path);
+    // Copy the .dll file to the deps folder, so that it can be found at runtime
+    if let Some(path) = &source_dir {
+        let source_path = Path::new(path);
         let dest_path = Path::new(&env::var("OUT_DIR").unwrap()).join("../../../deps");
-        fs::copy(
-            source_path.join("..\\..\\NewTek NDI 3.8 SDK\\Lib\\x64\\Processing.NDI.Lib.x64.lib"),
-            dest_path.join("Processing.NDI.Lib.x64.lib"),
-        )
-        .expect("copy Processing.NDI.Lib.x64.lib");
         fs::copy(
             source_path.join("Processing.NDI.Lib.x64.dll"),
             dest_path.join("Processing.NDI.Lib.x64.dll"),
@@ -77,32 +137,107 @@ fn main() {
         .expect("copy Processing.NDI.Lib.x64.dll");
     }
 
-    if cfg!(not(feature = "dynamic-link")) {
+    // The .lib file is only shipped with the SDK, whose folder name changes with each version
+    println!("cargo:rerun-if-env-changed=NDI_SDK_DIR");
+    let lib_dirs = [
+        env::var("NDI_SDK_DIR")
+            .ok()
+            .map(|p| Path::new(&p).join("Lib").join("x64")),
+        source_dir.map(PathBuf::from),
+    ];
+    for dir in lib_dirs.iter().flatten() {
+        if dir.join("Processing.NDI.Lib.x64.lib").exists() {
+            println!("cargo:rustc-link-search=native={}", dir.display());
+            break;
+        }
+    }
+
+    if !dynamic_link() {
         // Static link against it
         println!("cargo:rustc-link-lib=Processing.NDI.Lib.x64");
     }
 }
 
-#[cfg(target_os = "linux")]
-fn main() {
+fn build_linux() {
     let source_dir = choose_source_dir();
 
     // Copy the .so files to the deps folder, to make it build
     if let Some(path) = source_dir {
         let source_path = Path::new(&path);
         let dest_path = Path::new(&env::var("OUT_DIR").unwrap()).join("../../../deps");
-        fs::copy(source_path.join("libndi.so.3"), dest_path.join("libndi.so.3")).expect("copy libndi.so.3");
 
-        let sl_res = std::os::unix::fs::symlink(Path::new("libndi.so.3"), dest_path.join("libndi.so"));
-        if let Err(e) = sl_res {
-            if e.kind() != ErrorKind::AlreadyExists {
-                panic!("Unknown error: {}", e);
-            }
-        }
+        // Newer runtimes are versioned differently
+        let lib_name = ["libndi.so.6", "libndi.so.5", "libndi.so.4", "libndi.so.3"]
+            .iter()
+            .find(|name| source_path.join(name).exists())
+            .expect("libndi.so not found in the runtime directory");
+        fs::copy(source_path.join(lib_name), dest_path.join(lib_name)).expect("copy libndi.so");
+
+        symlink(Path::new(lib_name), &dest_path.join("libndi.so"));
     }
 
-    if cfg!(not(feature = "dynamic-link")) {
+    if !dynamic_link() {
         // Static link against it
         println!("cargo:rustc-link-lib=ndi");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) {
+    if let Err(e) = std::os::unix::fs::symlink(original, link) {
+        if e.kind() != ErrorKind::AlreadyExists {
+            panic!("Unknown error: {}", e);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn symlink(original: &Path, link: &Path) {
+    // Symlinks need extra permissions on windows, so fall back to a second copy
+    if let Err(e) = fs::copy(link.with_file_name(original), link) {
+        if e.kind() != ErrorKind::AlreadyExists {
+            panic!("Unknown error: {}", e);
+        }
+    }
+}
+
+fn build_android() {
+    // The SDK ships a library per ABI
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").expect("CARGO_CFG_TARGET_ARCH environment variable not set");
+    let abi = match target_arch.as_str() {
+        "aarch64" => "arm64-v8a",
+        "arm" => "armeabi-v7a",
+        "x86" => "x86",
+        "x86_64" => "x86_64",
+        arch => panic!("Unsupported android architecture: {}", arch),
+    };
+
+    println!("cargo:rerun-if-env-changed=NDI_SDK_DIR");
+    let ndi_sdk_path = env::var("NDI_SDK_DIR").expect("NDI_SDK_DIR environment variable not set");
+    let lib_path = Path::new(&ndi_sdk_path).join("lib").join(abi);
+    if !lib_path.join("libndi.so").exists() {
+        panic!("libndi.so not found in {}", lib_path.display());
+    }
+
+    println!("cargo:rustc-link-search=native={}", lib_path.display());
+    if !dynamic_link() {
+        println!("cargo:rustc-link-lib=dylib=ndi");
+    }
+}
+
+fn build_ios() {
+    // iOS does not allow loading libraries at runtime, so the static library is always linked
+    println!("cargo:rerun-if-env-changed=NDI_SDK_DIR");
+    let ndi_sdk_path = env::var("NDI_SDK_DIR").expect("NDI_SDK_DIR environment variable not set");
+    let lib_path = Path::new(&ndi_sdk_path).join("lib").join("iOS");
+    if !lib_path.join("libndi_ios.a").exists() {
+        panic!("libndi_ios.a not found in {}", lib_path.display());
+    }
+
+    println!("cargo:rustc-link-search=native={}", lib_path.display());
+    println!("cargo:rustc-link-lib=static=ndi_ios");
+    println!("cargo:rustc-link-lib=c++");
+    println!("cargo:rustc-link-lib=framework=Accelerate");
+    println!("cargo:rustc-link-lib=framework=VideoToolbox");
+    println!("cargo:rustc-link-lib=framework=AudioToolbox");
+}
@@ -1,14 +1,17 @@
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
-use ndi_sdk::receive::{
-    ReceiveBandwidth, ReceiveCaptureResult, ReceiveColorFormat, ReceiveInstanceExt,
-};
+use ndi_sdk::receive::{ReceiveBandwidth, ReceiveColorFormat, ReceiveInstanceExt, VideoFormat};
 use std::sync::Arc;
 
+// The most frames to drop in one update, in case frames arrive faster than they can be captured
+const MAX_FRAMES_TO_DISCARD: usize = 6;
+
 #[derive(Resource)]
 struct NDIReceiver {
     receiver: Arc<ndi_sdk::receive::ReceiveInstance>,
     image_handle: Option<Handle<Image>>,
+    // The format of the last frame shown, to detect the source switching modes
+    format: Option<VideoFormat>,
 }
 
 #[derive(Resource)]
@@ -19,8 +22,8 @@ struct NDISprite {
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
-        // Insert the NDIReceiver as a non-send resource during app initialization
-        .insert_non_send_resource(setup_ndi_receiver())
+        // The receiver is Send and Sync, so it can be a normal resource
+        .insert_resource(setup_ndi_receiver())
         .add_systems(Startup, setup_graphics)
         .add_systems(Update, receive_ndi_frames)
         .run();
@@ -32,38 +35,17 @@ fn setup_ndi_receiver() -> NDIReceiver {
     let instance = ndi_sdk::load().expect("Failed to load NDI SDK");
     println!("NDI SDK loaded.");
 
-    let source = {
-        println!("Creating NDI finder instance...");
-        let finder = instance
-            .create_find_instance(true)
-            .expect("Failed to create NDI finder instance");
-        println!("NDI finder instance created.");
-
-        loop {
-            println!("Waiting for NDI sources...");
-            finder.wait_for_sources(1000);
-            let sources = finder.get_current_sources();
-            println!("Found {} NDI sources.", sources.len());
-            if !sources.is_empty() {
-                println!("Using source: {}", sources[0].name);
-                break sources[0].clone();
-            }
-        }
-    };
-
-    // Create NDI receiver with RGBA format to avoid conversion
-    println!("Creating NDI receiver...");
+    // Connect to the first source found, with RGBA format to avoid conversion
+    println!("Waiting for NDI sources...");
     let receiver = instance
-        .create_receive_instance(ReceiveBandwidth::Highest, ReceiveColorFormat::RgbxRgba)
-        .expect("Failed to create NDI receiver");
-    println!("NDI receiver created.");
-
-    receiver.connect(Some(&source));
+        .connect_to_first_source(ReceiveBandwidth::Highest, ReceiveColorFormat::RgbxRgba, 30_000)
+        .expect("Failed to connect to an NDI source");
     println!("NDI receiver connected to source.");
 
     NDIReceiver {
         receiver: receiver,
         image_handle: None,
+        format: None,
     }
 }
 
@@ -111,57 +93,58 @@ fn setup_graphics(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
 }
 
 fn receive_ndi_frames(
-    mut ndi_receiver: NonSendMut<NDIReceiver>,
+    mut ndi_receiver: ResMut<NDIReceiver>,
     ndi_sprite: Res<NDISprite>,
     mut images: ResMut<Assets<Image>>,
     mut query: Query<(&mut Handle<Image>, &mut Sprite)>,
 ) {
-    // Initialize an Option to hold the latest frame
-    let mut latest_video_frame: Option<ndi_sdk::receive::VideoFrame> = None;
-
-    let mut frame_count = 0;
-    let max_frames_to_discard = 6; // Adjust based on your needs
-    // Receive all available frames and keep the latest one
-    loop {
-        match ndi_receiver
-            .receiver
-            .receive_capture(true, false, false, 0)
-        {
-            Ok(ReceiveCaptureResult::Video(video)) => {
-                frame_count += 1;
-                if frame_count > max_frames_to_discard {
-                    // Keep the current frame and break
-                    latest_video_frame = Some(video);
-                    break;
-                } else {
-                    // Replace the previous frame
-                    if let Some(prev_frame) = latest_video_frame.replace(video) {
-                        drop(prev_frame);
-                    }
-                }
+    // Take only the newest frame, dropping any that queued up since the last update so the display doesn't lag
+    let latest_video_frame = match ndi_receiver.receiver.capture_latest_video(MAX_FRAMES_TO_DISCARD) {
+        Ok(latest) => latest.map(|latest| {
+            if latest.dropped > 0 {
+                println!("Dropped {} NDI frames", latest.dropped);
             }
-            Ok(ReceiveCaptureResult::None) => {
-                break;
+            latest.frame
+        }),
+        Err(e) => {
+            println!("Failed to receive NDI frame: {:?}", e);
+            return;
+        }
+    };
+
+    // Process the latest frame if available
+    if let Some(video) = latest_video_frame {
+        // A source can change resolution mid stream, in which case the texture has to be resized to match
+        if let Some(format) = &ndi_receiver.format {
+            if video.format_changed_since(format) {
+                println!("Source format changed to {}x{}", video.width, video.height);
             }
-            Ok(_) => {}
-            Err(e) => {
-                println!("Failed to receive NDI frame: {:?}", e);
+        }
+        ndi_receiver.format = Some(video.format());
+
+        // Convert straight into the existing image when the size hasn't changed, to avoid allocating every frame
+        if let Some(image) = ndi_receiver.image_handle.as_ref().and_then(|handle| images.get_mut(handle)) {
+            if video.to_rgba_into(&mut image.data) {
                 return;
             }
         }
-    }
 
-    // Process the latest frame if available
-    if let Some(video) = latest_video_frame {
-        if let Some(data) = video.lock_data() {
+        // The SDK may hand back a different format to the one requested, and rows may be padded, so always convert
+        // to packed RGBA rather than copying the data directly
+        if let Some(data) = video.to_rgba() {
             let width = video.width as usize;
             let height = video.height as usize;
 
             // Update the existing image asset
             if let Some(image_handle) = &ndi_receiver.image_handle {
                 if let Some(image) = images.get_mut(image_handle) {
-                    // Update the image data
-                    image.data = data.to_vec();
+                    // Update the image data, and its size as the conversion above only fails when it has changed
+                    image.data = data;
+                    image.texture_descriptor.size = Extent3d {
+                        width: width as u32,
+                        height: height as u32,
+                        depth_or_array_layers: 1,
+                    };
 
                     // Update the sprite's size if necessary
                     if let Ok((_, mut sprite)) = query.get_mut(ndi_sprite.entity) {
@@ -179,7 +162,7 @@ fn receive_ndi_frames(
                         depth_or_array_layers: 1,
                     },
                     TextureDimension::D2,
-                    data.to_vec(),
+                    data,
                     TextureFormat::Rgba8UnormSrgb,
                 );
 
@@ -198,7 +181,7 @@ fn receive_ndi_frames(
                 }
             }
         } else {
-            println!("Failed to lock video data.");
+            println!("Unsupported video format: {:?}", video.four_cc());
         }
 
         // Release the frame
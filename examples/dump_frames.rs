@@ -0,0 +1,55 @@
+extern crate ndi_sdk;
+
+use ndi_sdk::receive::{ReceiveBandwidth, ReceiveColorFormat, ReceiveInstanceExt};
+use std::env;
+
+// Connects to the first source found and saves the next few video frames as PNGs, without needing a GPU
+//
+// Run with `cargo run --example dump_frames --features image -- [frame count]`
+fn main() {
+    let count: usize = env::args().nth(1).and_then(|n| n.parse().ok()).unwrap_or(5);
+
+    println!("Waiting for a source...");
+    // Fastest avoids the SDK converting frames, as they are converted to RGBA when saved anyway
+    let receiver = ndi_sdk::connect_to_first_source(ReceiveBandwidth::Highest, ReceiveColorFormat::Fastest, 10_000)
+        .expect("Failed to connect to a source");
+    if let Some(source) = receiver.current_source() {
+        println!("Connected to {}", source.name);
+    }
+
+    let mut saved = 0;
+    // Give up after a run of timeouts or unconvertible frames, so a stalled source doesn't hang the example
+    let mut failures = 0;
+    while saved < count && failures < 10 {
+        match receiver.capture_video(5000) {
+            Ok(Some(video)) => {
+                let path = format!("frame_{}.png", saved);
+                match video.to_image_buffer() {
+                    Some(image) => {
+                        image.save(&path).expect("Failed to save frame");
+                        println!(
+                            "Saved {}x{} {:?} frame to {}",
+                            video.width,
+                            video.height,
+                            video.four_cc(),
+                            path
+                        );
+                        saved += 1;
+                        failures = 0;
+                    }
+                    None => {
+                        failures += 1;
+                        println!("Unable to convert {:?} frame", video.four_cc());
+                    }
+                }
+            }
+            Ok(None) => {
+                failures += 1;
+                println!("No frame received");
+            }
+            Err(e) => panic!("Failed to capture a frame: {:?}", e),
+        }
+    }
+
+    println!("Saved {} frames", saved);
+}
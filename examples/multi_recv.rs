@@ -0,0 +1,59 @@
+extern crate ndi_sdk;
+
+use ndi_sdk::receive::{ReceiveBandwidth, ReceiveColorFormat, ReceiveInstanceExt};
+use ndi_sdk::NdiError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let instance = ndi_sdk::load().expect("Failed to construct NDI instance");
+
+    // One finder is enough for every receiver
+    let finder = instance
+        .create_find_instance(true)
+        .expect("Expected find instance to be created");
+    finder.wait_for_sources(5000);
+    let sources = finder.get_current_sources();
+    println!("Found {} sources", sources.len());
+
+    // Create a receiver per source, each capturing on its own thread
+    let threads: Vec<_> = sources
+        .into_iter()
+        .take(4)
+        .map(|source| {
+            let receiver = instance
+                .create_receive_builder()
+                .with_bandwidth(ReceiveBandwidth::Lowest)
+                .with_color_format(ReceiveColorFormat::Fastest)
+                .with_source(source.clone())
+                .build()
+                .expect("create receiver");
+
+            thread::spawn(move || {
+                let start = Instant::now();
+                let mut frames = 0;
+                let mut timeouts = 0;
+                while start.elapsed() < Duration::from_secs(10) {
+                    match receiver.capture_video(1000) {
+                        Ok(Some(_video)) => frames += 1,
+                        Ok(None) => timeouts += 1,
+                        // The SDK keeps trying to reconnect, so carry on capturing
+                        Err(NdiError::ConnectionLost) => println!("{}: connection lost", source.name),
+                        Err(e) => {
+                            println!("{}: failed to capture: {:?}", source.name, e);
+                            break;
+                        }
+                    }
+                }
+                println!(
+                    "{}: {} frames and {} timeouts in 10 seconds",
+                    source.name, frames, timeouts
+                );
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().expect("receiver thread panicked");
+    }
+}
@@ -16,7 +16,7 @@ fn main() {
         // Wait until there is one source
         loop {
             println!("Looking for sources ...");
-            finder.wait_for_sources(1000);
+            finder.poll_sources();
             let sources = finder.get_current_sources();
             if sources.len() > 0 {
                 break sources[0].clone();
@@ -32,7 +32,7 @@ fn main() {
         .expect("create receiver");
 
     // Connect to our sources
-    assert!(receiver.connect(Some(&source)));
+    receiver.connect(Some(&source)).expect("connect to source");
 
     let start = Instant::now();
     loop {
@@ -53,11 +53,16 @@ fn main() {
                     }
                 }
                 ReceiveCaptureResult::Audio(audio) => {
-                    println!("Audio data received ({} samples).", audio.sample_count);
+                    println!(
+                        "Audio data received ({} samples, {} channels).",
+                        audio.sample_count, audio.channel_count
+                    );
                     if let Some(data) = audio.lock_data() {
-                        println!("  Got {} bytes", data.len());
+                        println!("  Got {} samples", data.len());
                     }
                 }
+                ReceiveCaptureResult::StatusChange => println!("Source status changed."),
+                ReceiveCaptureResult::Error => println!("Capture error, the connection may have been lost."),
                 _ => {}
             },
         }
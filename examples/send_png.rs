@@ -15,7 +15,7 @@ fn main() {
 
     // Create an NDI source that is called "My PNG" and is clocked to the video.
     let mut sender = instance
-        .create_send_instance("My PNG".to_string(), false, false)
+        .create_send_instance("My PNG".to_string(), None, false, false)
         .expect("Expected sender instance to be created");
 
     // We are going to create a frame
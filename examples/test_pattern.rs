@@ -0,0 +1,19 @@
+extern crate ndi_sdk;
+
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let instance = ndi_sdk::load().expect("Failed to construct NDI instance");
+
+    // Send 1080p30 color bars, which receivers on the network can connect to
+    let pattern = instance
+        .create_test_pattern("Test Pattern".to_string(), 1920, 1080, 30)
+        .expect("Expected test pattern to be created");
+    println!("Sending test pattern for one minute");
+
+    thread::sleep(Duration::from_secs(60));
+
+    // Dropping the pattern stops the source
+    drop(pattern);
+}
@@ -0,0 +1,127 @@
+//! Owned audio data, for processing audio after it has been received
+//!
+//! An `AudioFrame` is held by the SDK, so it can't be modified. Copying it into an `AudioBuffer` allows it to be
+//! resampled or remapped to fit the output device.
+
+use crate::util::interleave_audio;
+
+/// The gain applied to the centre and surround channels when downmixing 5.1 to stereo, -3dB
+const DOWNMIX_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Planar 32bit float audio, with each channel stored one after another
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBuffer {
+    pub sample_rate: i32,
+    pub channel_count: usize,
+    /// The number of samples in each channel
+    pub sample_count: usize,
+    /// The timecode of the audio, in 100ns units
+    pub timecode: i64,
+    /// The samples, `sample_count` for each channel in turn
+    pub data: Vec<f32>,
+}
+impl AudioBuffer {
+    /// The samples of a single channel
+    pub fn channel(&self, channel: usize) -> Option<&[f32]> {
+        if channel < self.channel_count {
+            Some(&self.data[channel * self.sample_count..(channel + 1) * self.sample_count])
+        } else {
+            None
+        }
+    }
+
+    /// Copy the samples into a new buffer, interleaving the channels
+    pub fn to_interleaved(&self) -> Vec<f32> {
+        interleave_audio(&self.data, self.channel_count, self.sample_count, self.sample_count)
+    }
+
+    /// Resample the audio to a different sample rate, using linear interpolation
+    ///
+    /// This is intended for matching the rate of an output device, rather than for high quality conversion. If the
+    /// rates already match, the buffer is cloned.
+    pub fn resample(&self, target_rate: i32) -> AudioBuffer {
+        if target_rate == self.sample_rate || self.sample_rate <= 0 || target_rate <= 0 || self.sample_count == 0 {
+            return AudioBuffer {
+                sample_rate: if target_rate > 0 { target_rate } else { self.sample_rate },
+                ..self.clone()
+            };
+        }
+
+        let ratio = self.sample_rate as f64 / target_rate as f64;
+        let sample_count = (self.sample_count as f64 / ratio).round() as usize;
+
+        let mut data = Vec::with_capacity(sample_count * self.channel_count);
+        for channel in 0..self.channel_count {
+            let input = &self.data[channel * self.sample_count..(channel + 1) * self.sample_count];
+            for i in 0..sample_count {
+                let pos = i as f64 * ratio;
+                let index = pos as usize;
+                let frac = (pos - index as f64) as f32;
+
+                let a = input[index.min(input.len() - 1)];
+                let b = input[(index + 1).min(input.len() - 1)];
+                data.push(a + (b - a) * frac);
+            }
+        }
+
+        AudioBuffer {
+            sample_rate: target_rate,
+            channel_count: self.channel_count,
+            sample_count,
+            timecode: self.timecode,
+            data,
+        }
+    }
+
+    /// Convert the audio to a different number of channels
+    ///
+    /// NDI does not define a channel layout, so the usual order of L, R, C, LFE, Ls, Rs is assumed for 6 channels.
+    /// The mapping used is:
+    ///
+    /// * 5.1 to stereo uses the ITU-R BS.775 matrix, `L' = L + 0.707 C + 0.707 Ls` and `R' = R + 0.707 C + 0.707 Rs`,
+    ///   with the LFE discarded. The result is not normalised, so may exceed 1.0
+    /// * Any other layout to stereo keeps the first two channels
+    /// * Anything to mono averages the stereo downmix
+    /// * Mono to more channels copies it to every channel
+    /// * Otherwise existing channels are kept, extra channels are silent and any others are dropped
+    ///
+    pub fn remap_channels(&self, target_channels: usize) -> AudioBuffer {
+        let count = self.sample_count;
+        let data = if target_channels == self.channel_count {
+            self.data.clone()
+        } else if self.channel_count == 0 || target_channels == 0 {
+            vec![0.0; target_channels * count]
+        } else if target_channels == 1 {
+            let stereo = self.remap_channels(2);
+            let (left, right) = stereo.data.split_at(count);
+            left.iter().zip(right).map(|(l, r)| (l + r) * 0.5).collect()
+        } else if self.channel_count == 1 {
+            self.data.repeat(target_channels)
+        } else if target_channels == 2 && self.channel_count == 6 {
+            let ch = |c: usize| &self.data[c * count..(c + 1) * count];
+            let (l, r, c, ls, rs) = (ch(0), ch(1), ch(2), ch(4), ch(5));
+
+            let mut res = Vec::with_capacity(2 * count);
+            for i in 0..count {
+                res.push(l[i] + DOWNMIX_GAIN * (c[i] + ls[i]));
+            }
+            for i in 0..count {
+                res.push(r[i] + DOWNMIX_GAIN * (c[i] + rs[i]));
+            }
+            res
+        } else {
+            let kept = self.channel_count.min(target_channels);
+            let mut res = self.data[..kept * count].to_vec();
+            res.resize(target_channels * count, 0.0);
+            res
+        };
+
+        AudioBuffer {
+            sample_rate: self.sample_rate,
+            channel_count: target_channels,
+            sample_count: count,
+            timecode: self.timecode,
+            data,
+        }
+    }
+}
@@ -0,0 +1,847 @@
+//! Conversions between the pixel formats used by NDI
+//!
+//! These operate on raw frame data, so can be used with data from `VideoFrame::lock_data()` or with buffers that are
+//! going to be sent. Strides are in bytes, and outputs are always tightly packed.
+
+use crate::receive::FourCCType;
+
+/// Fixed point (8 bit fraction) coefficients of a YUV to RGB matrix, for limited range YUV
+#[derive(Debug, Copy, Clone)]
+struct YuvCoefficients {
+    rv: i32,
+    gu: i32,
+    gv: i32,
+    bu: i32,
+}
+
+const BT601: YuvCoefficients = YuvCoefficients {
+    rv: 409,
+    gu: 100,
+    gv: 208,
+    bu: 516,
+};
+const BT709: YuvCoefficients = YuvCoefficients {
+    rv: 459,
+    gu: 55,
+    gv: 136,
+    bu: 541,
+};
+
+/// The matrix used to convert between YUV and RGB
+///
+/// NDI does not signal the color space of frames, and instead uses BT.601 for SD resolutions and BT.709 for everything
+/// else. In both cases the data is gamma encoded rather than linear, so RGBA output is close enough to sRGB to be
+/// used with sRGB texture formats. Linear space renderers need to decode it first.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ColorMatrix {
+    Bt601,
+    Bt709,
+}
+impl ColorMatrix {
+    /// The matrix NDI uses for frames of the given height
+    pub fn for_height(height: usize) -> ColorMatrix {
+        if height < 720 {
+            ColorMatrix::Bt601
+        } else {
+            ColorMatrix::Bt709
+        }
+    }
+}
+
+fn coefficients_for(height: usize) -> YuvCoefficients {
+    match ColorMatrix::for_height(height) {
+        ColorMatrix::Bt601 => BT601,
+        ColorMatrix::Bt709 => BT709,
+    }
+}
+
+#[inline]
+fn clamp_u8(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+#[inline]
+fn yuv_to_rgba(y: u8, u: u8, v: u8, coef: &YuvCoefficients, out: &mut [u8]) {
+    let c = (y as i32 - 16) * 298;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+
+    out[0] = clamp_u8((c + coef.rv * e + 128) >> 8);
+    out[1] = clamp_u8((c - coef.gu * d - coef.gv * e + 128) >> 8);
+    out[2] = clamp_u8((c + coef.bu * d + 128) >> 8);
+    out[3] = 255;
+}
+
+/// The number of bytes of frame data, including any extra planes after the first
+pub fn frame_data_len(four_cc: FourCCType, width: usize, height: usize, stride: usize) -> usize {
+    match four_cc {
+        FourCCType::UYVY | FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX => stride * height,
+        // The alpha plane follows the UYVY data, with a stride of the width
+        FourCCType::UYVA => stride * height + width * height,
+        // The interleaved UV plane has the same stride as the Y plane
+        FourCCType::NV12 => stride * height + stride * height.div_ceil(2),
+        // Each chroma plane has half the stride of the Y plane, rounded up so odd widths keep their last column
+        FourCCType::YV12 | FourCCType::I420 => stride * height + 2 * stride.div_ceil(2) * height.div_ceil(2),
+    }
+}
+
+/// The smallest valid stride of the first plane
+pub(crate) fn min_stride(four_cc: FourCCType, width: usize) -> usize {
+    match four_cc {
+        FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX => width * 4,
+        FourCCType::UYVY | FourCCType::UYVA => width.div_ceil(2) * 4,
+        // The UV plane shares the stride, and needs a pair of bytes for every 2 pixels
+        FourCCType::NV12 => width.div_ceil(2) * 2,
+        FourCCType::YV12 | FourCCType::I420 => width,
+    }
+}
+
+/// Copy the rows of an image, dropping any padding at the end of each row
+fn pack_rows(data: &[u8], row_bytes: usize, stride: usize, out: &mut [u8]) {
+    for (row, out_row) in data.chunks(stride).zip(out.chunks_exact_mut(row_bytes)) {
+        out_row.copy_from_slice(&row[..row_bytes]);
+    }
+}
+
+fn fill_alpha(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+}
+
+/// Convert tightly packed BGRA or BGRX into RGBA or RGBX, by swapping the red and blue channels in place
+///
+/// This is the cheapest conversion, so when receiving with `ReceiveColorFormat::Fastest` it is usually quicker to
+/// receive BGRA and swizzle it than to have the SDK convert to RGBA. Each pixel is handled independently in fixed
+/// size chunks, so the compiler can vectorize the loop. Any trailing bytes that don't make a whole pixel are left
+/// untouched.
+pub fn bgra_to_rgba_in_place(data: &mut [u8]) {
+    for px in data.chunks_exact_mut(4) {
+        let (b, r) = (px[0], px[2]);
+        px[0] = r;
+        px[2] = b;
+    }
+}
+
+/// Convert tightly packed RGBA or RGBX into BGRA or BGRX, eg before sending. This is the same swap as
+/// `bgra_to_rgba_in_place`
+pub fn rgba_to_bgra_in_place(data: &mut [u8]) {
+    bgra_to_rgba_in_place(data)
+}
+
+fn uyvy_to_rgba(data: &[u8], width: usize, height: usize, stride: usize, out: &mut [u8]) {
+    let coef = coefficients_for(height);
+    for (row, out_row) in data.chunks(stride).zip(out.chunks_exact_mut(width * 4)) {
+        // Each 4 bytes of UYVY make up 2 pixels, which share the chroma
+        for (px, out) in row.chunks_exact(4).zip(out_row.chunks_mut(8)) {
+            yuv_to_rgba(px[1], px[0], px[2], &coef, &mut out[0..4]);
+            if out.len() == 8 {
+                yuv_to_rgba(px[3], px[0], px[2], &coef, &mut out[4..8]);
+            }
+        }
+    }
+}
+
+/// Convert UYVY followed by an alpha plane. The alpha plane starts straight after the last row of UYVY data, and
+/// has one byte per pixel with a stride of the width. The alpha is straight, so it is copied over unchanged
+fn uyva_to_rgba(data: &[u8], width: usize, height: usize, stride: usize, out: &mut [u8]) {
+    uyvy_to_rgba(data, width, height, stride, out);
+
+    let alpha = &data[stride * height..stride * height + width * height];
+    for (px, a) in out.chunks_exact_mut(4).zip(alpha) {
+        px[3] = *a;
+    }
+}
+
+/// Convert planar 4:2:0 data. The chroma of each pixel is read from `u_offset` and `v_offset` plus the position of
+/// the pixel in the chroma plane, with rows `chroma_stride` bytes apart and pixels `chroma_step` bytes apart
+#[allow(clippy::too_many_arguments)]
+fn yuv420_to_rgba(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    u_offset: usize,
+    v_offset: usize,
+    chroma_stride: usize,
+    chroma_step: usize,
+    out: &mut [u8],
+) {
+    let coef = coefficients_for(height);
+    for (y, out_row) in out.chunks_exact_mut(width * 4).enumerate() {
+        let luma = &data[y * stride..];
+        let chroma_row = (y / 2) * chroma_stride;
+        for (x, out) in out_row.chunks_exact_mut(4).enumerate() {
+            let chroma = chroma_row + (x / 2) * chroma_step;
+            yuv_to_rgba(luma[x], data[u_offset + chroma], data[v_offset + chroma], &coef, out);
+        }
+    }
+}
+
+/// Convert tightly packed RGBA with straight alpha, as used by NDI, into premultiplied alpha
+pub fn premultiply_alpha(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let a = px[3] as u32;
+        for c in &mut px[0..3] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Convert tightly packed RGBA with premultiplied alpha into straight alpha, as used by NDI
+pub fn unpremultiply_alpha(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let a = px[3] as u32;
+        if a == 0 {
+            continue;
+        }
+        for c in &mut px[0..3] {
+            *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+        }
+    }
+}
+
+/// Convert frame data of any supported format into tightly packed RGBA
+///
+/// # Returns
+///
+/// The RGBA data, or None if the format is not supported or the data is too short
+///
+pub fn to_rgba(data: &[u8], four_cc: FourCCType, width: usize, height: usize, stride: usize) -> Option<Vec<u8>> {
+    let mut res = vec![0; width * height * 4];
+    if to_rgba_into(data, four_cc, width, height, stride, &mut res) {
+        Some(res)
+    } else {
+        None
+    }
+}
+
+/// Convert frame data of any supported format into tightly packed RGBA, writing into an existing buffer
+///
+/// This avoids allocating, so the output can be written straight into a staging buffer or a reused Vec.
+///
+/// # Returns
+///
+/// false if the format is not supported, the data is too short, or `out` is not exactly `width * height * 4` bytes.
+/// A frame with no pixels converts to nothing, so only needs an empty `out`
+///
+pub fn to_rgba_into(
+    data: &[u8],
+    four_cc: FourCCType,
+    width: usize,
+    height: usize,
+    stride: usize,
+    out: &mut [u8],
+) -> bool {
+    if width == 0 || height == 0 {
+        return out.is_empty();
+    }
+    if data.len() < frame_data_len(four_cc, width, height, stride)
+        || stride < min_stride(four_cc, width)
+        || out.len() != width * height * 4
+    {
+        return false;
+    }
+
+    match four_cc {
+        FourCCType::RGBA => pack_rows(data, width * 4, stride, out),
+        FourCCType::RGBX => {
+            pack_rows(data, width * 4, stride, out);
+            fill_alpha(out);
+        }
+        FourCCType::BGRA => {
+            pack_rows(data, width * 4, stride, out);
+            bgra_to_rgba_in_place(out);
+        }
+        FourCCType::BGRX => {
+            pack_rows(data, width * 4, stride, out);
+            bgra_to_rgba_in_place(out);
+            fill_alpha(out);
+        }
+        FourCCType::UYVY => uyvy_to_rgba(data, width, height, stride, out),
+        FourCCType::NV12 => {
+            let uv = stride * height;
+            yuv420_to_rgba(data, width, height, stride, uv, uv + 1, stride, 2, out);
+        }
+        FourCCType::I420 => {
+            let u = stride * height;
+            let v = u + stride.div_ceil(2) * height.div_ceil(2);
+            yuv420_to_rgba(data, width, height, stride, u, v, stride.div_ceil(2), 1, out);
+        }
+        FourCCType::YV12 => {
+            let v = stride * height;
+            let u = v + stride.div_ceil(2) * height.div_ceil(2);
+            yuv420_to_rgba(data, width, height, stride, u, v, stride.div_ceil(2), 1, out);
+        }
+        FourCCType::UYVA => uyva_to_rgba(data, width, height, stride, out),
+    }
+
+    true
+}
+
+/// Planar 4:2:0 YUV, as accepted by most video encoders
+///
+/// Each plane is tightly packed. The chroma planes are `(width + 1) / 2` by `(height + 1) / 2`, so frames with an odd
+/// width or height keep their last column and row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct I420Planes {
+    pub y: Vec<u8>,
+    pub u: Vec<u8>,
+    pub v: Vec<u8>,
+}
+
+/// Copy the Y plane of planar data, dropping any padding
+fn copy_luma(data: &[u8], width: usize, height: usize, stride: usize, planes: &mut I420Planes) {
+    for row in data.chunks(stride).take(height) {
+        planes.y.extend_from_slice(&row[..width]);
+    }
+}
+
+/// Copy the chroma of planar 4:2:0 data into tightly packed U and V planes, with the same parameters as
+/// `yuv420_to_rgba`
+#[allow(clippy::too_many_arguments)]
+fn planar_chroma(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    u_offset: usize,
+    v_offset: usize,
+    chroma_stride: usize,
+    chroma_step: usize,
+    planes: &mut I420Planes,
+) {
+    for cy in 0..height.div_ceil(2) {
+        for cx in 0..width.div_ceil(2) {
+            let chroma = cy * chroma_stride + cx * chroma_step;
+            planes.u.push(data[u_offset + chroma]);
+            planes.v.push(data[v_offset + chroma]);
+        }
+    }
+}
+
+/// Convert UYVY data, averaging the chroma of each pair of rows as UYVY has chroma for every row
+fn uyvy_chroma(data: &[u8], width: usize, height: usize, stride: usize, planes: &mut I420Planes) {
+    let average = |a: u8, b: u8| (a as u16 + b as u16).div_ceil(2) as u8;
+    for cy in 0..height.div_ceil(2) {
+        let row0 = &data[2 * cy * stride..];
+        let row1 = &data[(2 * cy + 1).min(height - 1) * stride..];
+        for cx in 0..width.div_ceil(2) {
+            planes.u.push(average(row0[cx * 4], row1[cx * 4]));
+            planes.v.push(average(row0[cx * 4 + 2], row1[cx * 4 + 2]));
+        }
+    }
+}
+
+/// Convert YUV frame data into I420 planes, eg for feeding an encoder
+///
+/// Any alpha is dropped. RGB formats are not supported, as converting them would need a choice of color matrix that
+/// the encoder should make.
+///
+/// # Returns
+///
+/// The planes, or None if the format is not supported or the data is too short
+///
+pub fn to_i420(data: &[u8], four_cc: FourCCType, width: usize, height: usize, stride: usize) -> Option<I420Planes> {
+    if width == 0 || height == 0 {
+        let supported = !matches!(
+            four_cc,
+            FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX
+        );
+        return if supported {
+            Some(I420Planes {
+                y: Vec::new(),
+                u: Vec::new(),
+                v: Vec::new(),
+            })
+        } else {
+            None
+        };
+    }
+    if data.len() < frame_data_len(four_cc, width, height, stride) || stride < min_stride(four_cc, width) {
+        return None;
+    }
+
+    let chroma_len = width.div_ceil(2) * height.div_ceil(2);
+    let mut planes = I420Planes {
+        y: Vec::with_capacity(width * height),
+        u: Vec::with_capacity(chroma_len),
+        v: Vec::with_capacity(chroma_len),
+    };
+
+    match four_cc {
+        FourCCType::UYVY | FourCCType::UYVA => {
+            for row in data.chunks(stride).take(height) {
+                planes.y.extend((0..width).map(|x| row[(x / 2) * 4 + 1 + (x % 2) * 2]));
+            }
+            uyvy_chroma(data, width, height, stride, &mut planes);
+        }
+        FourCCType::NV12 => {
+            copy_luma(data, width, height, stride, &mut planes);
+            let uv = stride * height;
+            planar_chroma(data, width, height, uv, uv + 1, stride, 2, &mut planes);
+        }
+        FourCCType::I420 => {
+            copy_luma(data, width, height, stride, &mut planes);
+            let u = stride * height;
+            let v = u + stride.div_ceil(2) * height.div_ceil(2);
+            planar_chroma(data, width, height, u, v, stride.div_ceil(2), 1, &mut planes);
+        }
+        FourCCType::YV12 => {
+            copy_luma(data, width, height, stride, &mut planes);
+            let v = stride * height;
+            let u = v + stride.div_ceil(2) * height.div_ceil(2);
+            planar_chroma(data, width, height, u, v, stride.div_ceil(2), 1, &mut planes);
+        }
+        FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX => return None,
+    }
+
+    Some(planes)
+}
+
+/// Check that packed frame data can be flipped, returning the number of bytes in each row
+fn flip_row_bytes(data: &[u8], four_cc: FourCCType, width: usize, height: usize, stride: usize) -> Option<usize> {
+    match four_cc {
+        FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX | FourCCType::UYVY => {}
+        FourCCType::UYVA | FourCCType::NV12 | FourCCType::YV12 | FourCCType::I420 => return None,
+    }
+    let row_bytes = min_stride(four_cc, width);
+    if stride < row_bytes || data.len() < frame_data_len(four_cc, width, height, stride) {
+        return None;
+    }
+    Some(row_bytes)
+}
+
+/// Mirror packed frame data left to right, eg for webcam style sources
+///
+/// # Returns
+///
+/// The flipped data, tightly packed, or None for planar formats and UYVA, or if the data is too short. UYVY is
+/// mirrored a pair of pixels at a time, so frames with an odd width gain their padding pixel on the left
+///
+pub fn flip_horizontal(
+    data: &[u8],
+    four_cc: FourCCType,
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Option<Vec<u8>> {
+    let row_bytes = flip_row_bytes(data, four_cc, width, height, stride)?;
+    if row_bytes == 0 || height == 0 {
+        return Some(Vec::new());
+    }
+    let mut res = Vec::with_capacity(row_bytes * height);
+    for row in data.chunks(stride).take(height) {
+        for px in row[..row_bytes].chunks_exact(4).rev() {
+            if four_cc == FourCCType::UYVY {
+                // The pair shares its chroma, so only the two luma samples swap
+                res.extend_from_slice(&[px[0], px[3], px[2], px[1]]);
+            } else {
+                res.extend_from_slice(px);
+            }
+        }
+    }
+    Some(res)
+}
+
+/// Flip packed frame data upside down
+///
+/// # Returns
+///
+/// The flipped data, tightly packed, or None for planar formats and UYVA, or if the data is too short
+///
+pub fn flip_vertical(data: &[u8], four_cc: FourCCType, width: usize, height: usize, stride: usize) -> Option<Vec<u8>> {
+    let row_bytes = flip_row_bytes(data, four_cc, width, height, stride)?;
+    if row_bytes == 0 || height == 0 {
+        return Some(Vec::new());
+    }
+    let mut res = vec![0; row_bytes * height];
+    for (y, out_row) in res.chunks_exact_mut(row_bytes).rev().enumerate() {
+        out_row.copy_from_slice(&data[y * stride..y * stride + row_bytes]);
+    }
+    Some(res)
+}
+
+/// Tightly packed RGBA with its size, as produced by cropping and scaling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaFrame {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+/// Copy a rectangle out of tightly packed RGBA
+///
+/// The rectangle is clamped to the bounds of the image, so the result may be smaller than requested, or empty if it
+/// is entirely outside. Returns None if `rgba` is shorter than `width * height * 4` bytes, or that overflows
+pub fn crop_rgba(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    crop_width: usize,
+    crop_height: usize,
+) -> Option<RgbaFrame> {
+    if rgba.len() < width.checked_mul(height)?.checked_mul(4)? {
+        return None;
+    }
+
+    let x = x.min(width);
+    let y = y.min(height);
+    let crop_width = crop_width.min(width - x);
+    let crop_height = crop_height.min(height - y);
+
+    let mut data = Vec::with_capacity(crop_width * crop_height * 4);
+    for row in y..y + crop_height {
+        let start = (row * width + x) * 4;
+        data.extend_from_slice(&rgba[start..start + crop_width * 4]);
+    }
+    Some(RgbaFrame {
+        width: crop_width,
+        height: crop_height,
+        data,
+    })
+}
+
+/// Resize tightly packed RGBA using bilinear filtering
+///
+/// This is intended for thumbnails and small previews. Bilinear filtering only samples the 4 nearest pixels, so
+/// scaling down by more than half skips detail and can alias. Returns None if `rgba` is shorter than
+/// `width * height * 4` bytes, either size overflows, or the source is empty and the target isn't
+pub fn scale_rgba(rgba: &[u8], width: usize, height: usize, new_width: usize, new_height: usize) -> Option<RgbaFrame> {
+    let new_len = new_width.checked_mul(new_height)?.checked_mul(4)?;
+    if rgba.len() < width.checked_mul(height)?.checked_mul(4)? {
+        return None;
+    }
+    if new_len == 0 {
+        return Some(RgbaFrame {
+            width: new_width,
+            height: new_height,
+            data: Vec::new(),
+        });
+    }
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    // The position of an output pixel in the input, lining up pixel centres, and clamped to the edge pixels
+    let source_pos = |out: usize, out_len: usize, in_len: usize| {
+        let pos = (out as f32 + 0.5) * in_len as f32 / out_len as f32 - 0.5;
+        let pos = pos.max(0.0).min((in_len - 1) as f32);
+        let index = pos as usize;
+        (index, (index + 1).min(in_len - 1), pos - index as f32)
+    };
+
+    let mut data = Vec::with_capacity(new_len);
+    for oy in 0..new_height {
+        let (y0, y1, fy) = source_pos(oy, new_height, height);
+        let row0 = &rgba[y0 * width * 4..];
+        let row1 = &rgba[y1 * width * 4..];
+        for ox in 0..new_width {
+            let (x0, x1, fx) = source_pos(ox, new_width, width);
+            for c in 0..4 {
+                let top = row0[x0 * 4 + c] as f32 * (1.0 - fx) + row0[x1 * 4 + c] as f32 * fx;
+                let bottom = row1[x0 * 4 + c] as f32 * (1.0 - fx) + row1[x1 * 4 + c] as f32 * fx;
+                data.push((top * (1.0 - fy) + bottom * fy + 0.5) as u8);
+            }
+        }
+    }
+    Some(RgbaFrame {
+        width: new_width,
+        height: new_height,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: (u8, u8, u8) = (16, 128, 128);
+    const WHITE: (u8, u8, u8) = (235, 128, 128);
+    const RED_601: (u8, u8, u8) = (81, 90, 240);
+    const RED_709: (u8, u8, u8) = (63, 102, 240);
+
+    const YUV_FORMATS: [FourCCType; 5] = [
+        FourCCType::UYVY,
+        FourCCType::UYVA,
+        FourCCType::NV12,
+        FourCCType::I420,
+        FourCCType::YV12,
+    ];
+
+    /// A tightly packed frame filled with one YUV colour, and an alpha of 200 for UYVA
+    fn yuv_frame(four_cc: FourCCType, width: usize, height: usize, (y, u, v): (u8, u8, u8)) -> (Vec<u8>, usize) {
+        let stride = min_stride(four_cc, width);
+        let luma_len = stride * height;
+        let mut data = vec![0; frame_data_len(four_cc, width, height, stride)];
+        match four_cc {
+            FourCCType::UYVY | FourCCType::UYVA => {
+                for px in data[..luma_len].chunks_exact_mut(4) {
+                    px.copy_from_slice(&[u, y, v, y]);
+                }
+                data[luma_len..].fill(200);
+            }
+            FourCCType::NV12 => {
+                data[..luma_len].fill(y);
+                for uv in data[luma_len..].chunks_exact_mut(2) {
+                    uv.copy_from_slice(&[u, v]);
+                }
+            }
+            FourCCType::I420 | FourCCType::YV12 => {
+                let chroma_len = stride.div_ceil(2) * height.div_ceil(2);
+                let (first, second) = if four_cc == FourCCType::I420 { (u, v) } else { (v, u) };
+                data[..luma_len].fill(y);
+                data[luma_len..luma_len + chroma_len].fill(first);
+                data[luma_len + chroma_len..].fill(second);
+            }
+            _ => unreachable!(),
+        }
+        (data, stride)
+    }
+
+    fn assert_converts_to(four_cc: FourCCType, width: usize, height: usize, yuv: (u8, u8, u8), rgb: [u8; 3]) {
+        let (data, stride) = yuv_frame(four_cc, width, height, yuv);
+        let rgba = to_rgba(&data, four_cc, width, height, stride).unwrap();
+        let alpha = if four_cc == FourCCType::UYVA { 200 } else { 255 };
+        for px in rgba.chunks_exact(4) {
+            assert_eq!(px, [rgb[0], rgb[1], rgb[2], alpha], "{:?} {:?}", four_cc, yuv);
+        }
+    }
+
+    #[test]
+    fn yuv_bt601() {
+        // SD frames use BT.601
+        for four_cc in YUV_FORMATS.iter() {
+            assert_converts_to(*four_cc, 4, 2, BLACK, [0, 0, 0]);
+            assert_converts_to(*four_cc, 4, 2, WHITE, [255, 255, 255]);
+            assert_converts_to(*four_cc, 4, 2, RED_601, [255, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn yuv_bt709() {
+        // HD frames use BT.709, so the BT.601 red is no longer pure red
+        for four_cc in YUV_FORMATS.iter() {
+            assert_converts_to(*four_cc, 2, 720, BLACK, [0, 0, 0]);
+            assert_converts_to(*four_cc, 2, 720, WHITE, [255, 255, 255]);
+            assert_converts_to(*four_cc, 2, 720, RED_709, [255, 1, 0]);
+            assert_converts_to(*four_cc, 2, 720, RED_601, [255, 24, 0]);
+        }
+    }
+
+    #[test]
+    fn yuv_odd_size() {
+        for four_cc in YUV_FORMATS.iter() {
+            assert_converts_to(*four_cc, 1, 1, WHITE, [255, 255, 255]);
+            assert_converts_to(*four_cc, 3, 3, RED_601, [255, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn rgb_byte_order() {
+        assert_eq!(to_rgba(&[1, 2, 3, 4], FourCCType::RGBA, 1, 1, 4).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(
+            to_rgba(&[1, 2, 3, 4], FourCCType::RGBX, 1, 1, 4).unwrap(),
+            [1, 2, 3, 255]
+        );
+        assert_eq!(to_rgba(&[1, 2, 3, 4], FourCCType::BGRA, 1, 1, 4).unwrap(), [3, 2, 1, 4]);
+        assert_eq!(
+            to_rgba(&[1, 2, 3, 4], FourCCType::BGRX, 1, 1, 4).unwrap(),
+            [3, 2, 1, 255]
+        );
+    }
+
+    #[test]
+    fn rgb_padded_rows() {
+        // 1x2 BGRA with 4 bytes of padding after each row
+        let data = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0];
+        assert_eq!(
+            to_rgba(&data, FourCCType::BGRA, 1, 2, 8).unwrap(),
+            [3, 2, 1, 4, 7, 6, 5, 8]
+        );
+    }
+
+    #[test]
+    fn uyvy_byte_order() {
+        // U, Y0, V, Y1, with the two pixels differing only in luma
+        let rgba = to_rgba(&[128, 16, 128, 235], FourCCType::UYVY, 2, 1, 4).unwrap();
+        assert_eq!(rgba, [0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn zero_sized() {
+        assert_eq!(to_rgba(&[], FourCCType::I420, 0, 0, 0), Some(Vec::new()));
+        assert_eq!(to_rgba(&[], FourCCType::BGRA, 0, 4, 0), Some(Vec::new()));
+        assert!(to_rgba_into(&[], FourCCType::UYVY, 4, 0, 8, &mut []));
+        assert!(!to_rgba_into(&[], FourCCType::UYVY, 0, 4, 0, &mut [0; 4]));
+    }
+
+    #[test]
+    fn i420_single_pixel() {
+        let planes = to_i420(&[10, 40, 50], FourCCType::I420, 1, 1, 1).unwrap();
+        assert_eq!(planes.y, [10]);
+        assert_eq!(planes.u, [40]);
+        assert_eq!(planes.v, [50]);
+    }
+
+    #[test]
+    fn i420_odd_width() {
+        // The chroma planes have a stride of 2, rounded up from half of 3
+        let data = [10, 20, 30, 40, 41, 50, 51];
+        let planes = to_i420(&data, FourCCType::I420, 3, 1, 3).unwrap();
+        assert_eq!(planes.y, [10, 20, 30]);
+        assert_eq!(planes.u, [40, 41]);
+        assert_eq!(planes.v, [50, 51]);
+    }
+
+    #[test]
+    fn yv12_odd_width() {
+        // V comes before U
+        let data = [10, 20, 30, 50, 51, 40, 41];
+        let planes = to_i420(&data, FourCCType::YV12, 3, 1, 3).unwrap();
+        assert_eq!(planes.u, [40, 41]);
+        assert_eq!(planes.v, [50, 51]);
+    }
+
+    #[test]
+    fn i420_odd_height() {
+        let data = [1, 2, 3, 4, 5, 6, 40, 41, 50, 51];
+        let planes = to_i420(&data, FourCCType::I420, 2, 3, 2).unwrap();
+        assert_eq!(planes.y, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(planes.u, [40, 41]);
+        assert_eq!(planes.v, [50, 51]);
+    }
+
+    #[test]
+    fn nv12_odd_width() {
+        let data = [10, 20, 30, 0, 40, 50, 41, 51];
+        let planes = to_i420(&data, FourCCType::NV12, 3, 1, 4).unwrap();
+        assert_eq!(planes.y, [10, 20, 30]);
+        assert_eq!(planes.u, [40, 41]);
+        assert_eq!(planes.v, [50, 51]);
+
+        // The UV row needs 4 bytes, so a stride of 3 is too short
+        assert_eq!(to_i420(&[0; 6], FourCCType::NV12, 3, 1, 3), None);
+    }
+
+    #[test]
+    fn nv12_single_pixel() {
+        let planes = to_i420(&[10, 0, 40, 50], FourCCType::NV12, 1, 1, 2).unwrap();
+        assert_eq!(planes.y, [10]);
+        assert_eq!(planes.u, [40]);
+        assert_eq!(planes.v, [50]);
+    }
+
+    #[test]
+    fn i420_zero_sized() {
+        assert_eq!(to_i420(&[], FourCCType::NV12, 0, 0, 0).map(|p| p.y.len()), Some(0));
+        assert_eq!(to_i420(&[], FourCCType::UYVY, 0, 2, 0).map(|p| p.u.len()), Some(0));
+        assert_eq!(to_i420(&[], FourCCType::RGBA, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn uyvy_odd_size() {
+        // 3x3, with the last pixel of each row being padding, and the chroma of the last row not averaged
+        let data = [
+            10, 1, 20, 2, 30, 3, 40, 0, //
+            12, 4, 22, 5, 32, 6, 42, 0, //
+            50, 7, 60, 8, 70, 9, 80, 0,
+        ];
+        let planes = to_i420(&data, FourCCType::UYVY, 3, 3, 8).unwrap();
+        assert_eq!(planes.y, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(planes.u, [11, 31, 50, 70]);
+        assert_eq!(planes.v, [21, 41, 60, 80]);
+    }
+
+    /// A 2x2 RGBA frame with 4 bytes of padding after each row
+    const RGBA_PADDED: [u8; 24] = [
+        1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, //
+        3, 3, 3, 3, 4, 4, 4, 4, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn flip_rgba_horizontal() {
+        let flipped = flip_horizontal(&RGBA_PADDED, FourCCType::RGBA, 2, 2, 12).unwrap();
+        assert_eq!(flipped, [2, 2, 2, 2, 1, 1, 1, 1, 4, 4, 4, 4, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn flip_rgba_vertical() {
+        let flipped = flip_vertical(&RGBA_PADDED, FourCCType::RGBA, 2, 2, 12).unwrap();
+        assert_eq!(flipped, [3, 3, 3, 3, 4, 4, 4, 4, 1, 1, 1, 1, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn flip_uyvy_horizontal() {
+        // Two pairs per row, U Y0 V Y1, with 4 bytes of padding
+        let data = [
+            10, 1, 20, 2, 30, 3, 40, 4, 0, 0, 0, 0, //
+            50, 5, 60, 6, 70, 7, 80, 8, 0, 0, 0, 0,
+        ];
+        let flipped = flip_horizontal(&data, FourCCType::UYVY, 4, 2, 12).unwrap();
+        assert_eq!(flipped, [30, 4, 40, 3, 10, 2, 20, 1, 70, 8, 80, 7, 50, 6, 60, 5]);
+    }
+
+    #[test]
+    fn flip_uyvy_vertical() {
+        let data = [10, 1, 20, 2, 0, 0, 0, 0, 30, 3, 40, 4, 0, 0, 0, 0];
+        let flipped = flip_vertical(&data, FourCCType::UYVY, 2, 2, 8).unwrap();
+        assert_eq!(flipped, [30, 3, 40, 4, 10, 1, 20, 2]);
+    }
+
+    #[test]
+    fn flip_empty() {
+        for &four_cc in &[FourCCType::RGBA, FourCCType::UYVY] {
+            assert_eq!(flip_horizontal(&[], four_cc, 0, 2, 0), Some(Vec::new()));
+            assert_eq!(flip_vertical(&[], four_cc, 0, 2, 0), Some(Vec::new()));
+            assert_eq!(flip_horizontal(&[], four_cc, 2, 0, 8), Some(Vec::new()));
+            assert_eq!(flip_vertical(&[], four_cc, 2, 0, 8), Some(Vec::new()));
+        }
+    }
+
+    #[test]
+    fn flip_planar() {
+        assert_eq!(flip_horizontal(&[0; 6], FourCCType::I420, 2, 2, 2), None);
+        assert_eq!(flip_vertical(&[0; 6], FourCCType::NV12, 2, 2, 2), None);
+    }
+
+    #[test]
+    fn scale_to_empty() {
+        for &(width, height, new_width, new_height) in &[(0, 0, 0, 4), (0, 0, 4, 0), (2, 2, 0, 0), (2, 2, 3, 0)] {
+            let rgba = vec![0; width * height * 4];
+            let scaled = scale_rgba(&rgba, width, height, new_width, new_height).unwrap();
+            assert_eq!((scaled.width, scaled.height), (new_width, new_height));
+            assert!(scaled.data.is_empty());
+        }
+        assert_eq!(scale_rgba(&[], 0, 0, 2, 2), None);
+    }
+
+    #[test]
+    fn scale_overflow() {
+        assert_eq!(scale_rgba(&[], usize::MAX, 2, 1, 1), None);
+        assert_eq!(scale_rgba(&[0; 4], 1, 1, usize::MAX, 2), None);
+        assert_eq!(crop_rgba(&[], usize::MAX, 2, 0, 0, 1, 1), None);
+    }
+
+    #[test]
+    fn scale_up() {
+        // A black pixel beside a white one, doubled in width
+        let rgba = [0, 0, 0, 255, 255, 255, 255, 255];
+        let scaled = scale_rgba(&rgba, 2, 1, 4, 1).unwrap();
+        assert_eq!(
+            scaled.data,
+            [0, 0, 0, 255, 64, 64, 64, 255, 191, 191, 191, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn crop() {
+        let rgba: Vec<u8> = (0..16).collect();
+        // The bottom right pixel of 2x2, with the rectangle clamped to the image
+        let cropped = crop_rgba(&rgba, 2, 2, 1, 1, 5, 5).unwrap();
+        assert_eq!((cropped.width, cropped.height), (1, 1));
+        assert_eq!(cropped.data, [12, 13, 14, 15]);
+        assert!(crop_rgba(&rgba, 2, 2, 3, 0, 1, 1).unwrap().data.is_empty());
+    }
+}
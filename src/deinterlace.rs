@@ -0,0 +1,104 @@
+//! Reassembling interlaced video received as separate fields
+
+use crate::receive::{FourCCType, FrameFormatType, VideoFrame};
+
+/// A full frame woven together from two fields
+#[derive(Debug, Clone)]
+pub struct WovenFrame {
+    pub width: i32,
+    pub height: i32,
+    pub four_cc_type: FourCCType,
+    /// The number of bytes in each row of `data`. Rows are tightly packed
+    pub line_stride: usize,
+    /// The timecode of the first field
+    pub timecode: i64,
+    pub data: Vec<u8>,
+}
+
+/// Weaves `FrameFormatType::Field0` and `Field1` frames back into full frames
+///
+/// This is for receivers created with `with_allow_video_fields(true)`. Only packed formats are supported, so receive
+/// with one of the UYVY, BGRA or RGBA color formats rather than `Fastest`.
+#[derive(Debug, Default)]
+pub struct Deinterlacer {
+    pending: Option<PendingField>,
+}
+
+#[derive(Debug)]
+struct PendingField {
+    width: i32,
+    height: i32,
+    four_cc_type: FourCCType,
+    timecode: i64,
+    data: Vec<u8>,
+}
+
+/// The bytes in each row of a packed format, or None for planar formats
+fn packed_row_bytes(four_cc: FourCCType, width: usize) -> Option<usize> {
+    match four_cc {
+        FourCCType::BGRA | FourCCType::BGRX | FourCCType::RGBA | FourCCType::RGBX => Some(width * 4),
+        FourCCType::UYVY => Some(width.div_ceil(2) * 4),
+        FourCCType::UYVA | FourCCType::NV12 | FourCCType::YV12 | FourCCType::I420 => None,
+    }
+}
+
+impl Deinterlacer {
+    pub fn new() -> Deinterlacer {
+        Deinterlacer::default()
+    }
+
+    /// Add a field, returning the full frame once both fields have been received
+    ///
+    /// A `Field0` is held until the matching `Field1` arrives, and a `Field1` without a previous `Field0` of the same
+    /// format is dropped. Full frames and unsupported formats are ignored, returning None.
+    pub fn push(&mut self, frame: &VideoFrame) -> Option<WovenFrame> {
+        if !frame.frame_format_type.is_field() {
+            return None;
+        }
+        let row_bytes = packed_row_bytes(frame.four_cc_type, frame.width as usize)?;
+        let stride = frame.line_stride();
+        if stride < row_bytes || row_bytes == 0 {
+            return None;
+        }
+
+        let mut field = Vec::with_capacity(row_bytes * frame.height as usize);
+        {
+            let data = frame.lock_data()?;
+            for row in data.chunks(stride).take(frame.height as usize) {
+                field.extend_from_slice(row.get(..row_bytes)?);
+            }
+        }
+
+        if frame.frame_format_type == FrameFormatType::Field0 {
+            self.pending = Some(PendingField {
+                width: frame.width,
+                height: frame.height,
+                four_cc_type: frame.four_cc_type,
+                timecode: frame.timecode,
+                data: field,
+            });
+            return None;
+        }
+
+        let first = self.pending.take()?;
+        if first.width != frame.width || first.height != frame.height || first.four_cc_type != frame.four_cc_type {
+            return None;
+        }
+
+        // Field 0 holds the even lines and field 1 the odd lines
+        let mut data = Vec::with_capacity(first.data.len() * 2);
+        for (even, odd) in first.data.chunks(row_bytes).zip(field.chunks(row_bytes)) {
+            data.extend_from_slice(even);
+            data.extend_from_slice(odd);
+        }
+
+        Some(WovenFrame {
+            width: frame.width,
+            height: frame.height * 2,
+            four_cc_type: frame.four_cc_type,
+            line_stride: row_bytes,
+            timecode: first.timecode,
+            data,
+        })
+    }
+}
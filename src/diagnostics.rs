@@ -0,0 +1,30 @@
+//! Internal logging, forwarded to `tracing` or `log` when one of those features is enabled
+//!
+//! Events use the `ndi_sdk` target. When both features are enabled only `tracing` is used, as its `log` feature can
+//! forward events on if needed.
+
+macro_rules! ndi_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!(target: "ndi_sdk", $($arg)+);
+        #[cfg(all(feature = "log", not(feature = "tracing")))]
+        log::$level!(target: "ndi_sdk", $($arg)+);
+        #[cfg(not(any(feature = "log", feature = "tracing")))]
+        {
+            // Keep the arguments used, so that disabling logging doesn't cause warnings
+            let _ = || format!($($arg)+);
+        }
+    }};
+}
+
+macro_rules! ndi_debug {
+    ($($arg:tt)+) => { ndi_event!(debug, $($arg)+) };
+}
+
+macro_rules! ndi_info {
+    ($($arg:tt)+) => { ndi_event!(info, $($arg)+) };
+}
+
+macro_rules! ndi_warn {
+    ($($arg:tt)+) => { ndi_event!(warn, $($arg)+) };
+}
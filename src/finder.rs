@@ -1,19 +1,161 @@
 use crate::{sdk, NDIHandle};
-use std::ffi::CStr;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::hash::{Hash, Hasher};
 use std::ptr::null;
 use std::slice;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-#[derive(Clone)]
+/// A source found on the network
+///
+/// The name and url are copied out of the SDK's list when it is read, so a source stays valid after the finder
+/// refreshes or is dropped, and can be freely cloned and sent between threads.
+///
+/// Sources are compared and hashed by name only, as names are unique on a network while the address of a source can
+/// change, eg when it restarts. So a `HashSet<FindSource>` holds one entry per source.
+#[derive(Debug, Clone)]
 pub struct FindSource {
     pub name: String,
+    /// The address of the source, eg `192.168.1.5:5961`
     pub url: Option<String>,
 }
+impl PartialEq for FindSource {
+    fn eq(&self, other: &FindSource) -> bool {
+        self.name == other.name
+    }
+}
+impl Eq for FindSource {}
+impl Hash for FindSource {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+impl FindSource {
+    /// Create a source for a known machine, so it can be connected to without waiting for discovery
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The full name of the source, eg `MACHINE (Source)`
+    /// * `url` - The address of the source, eg `192.168.1.5:5961`
+    ///
+    pub fn from_url(name: &str, url: &str) -> FindSource {
+        FindSource {
+            name: name.to_string(),
+            url: Some(url.to_string()),
+        }
+    }
+
+    /// The address of the source, if known
+    pub fn url_address(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The machine part of a name of the form `MACHINE (Source)`
+    pub fn machine_name(&self) -> Option<&str> {
+        split_name(&self.name).map(|(machine, _)| machine)
+    }
+
+    /// The source part of a name of the form `MACHINE (Source)`
+    pub fn source_name(&self) -> Option<&str> {
+        split_name(&self.name).map(|(_, source)| source)
+    }
+
+    /// Whether the name of the source matches a query, ignoring case
+    ///
+    /// Names are in the form `MACHINE (Source)`, so this matches either any part of the full name, or exactly the
+    /// part in brackets
+    pub fn matches_name(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let name = self.name.to_lowercase();
+        if name.contains(&query) {
+            return true;
+        }
+
+        match split_name(&name) {
+            Some((_, source)) => source.trim() == query.trim(),
+            None => false,
+        }
+    }
+}
+
+/// The longest single wait made by the cancellable waits, so cancellation is noticed promptly
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Split a source name of the form `MACHINE (Source)` into the machine and source parts
+fn split_name(name: &str) -> Option<(&str, &str)> {
+    let start = name.find(" (")?;
+    let source = name[start + 2..].strip_suffix(')')?;
+    Some((&name[..start], source))
+}
+
+/// The host part of an address of the form `host:port`, or `[host]:port` for IPv6
+fn url_host(url: &str) -> &str {
+    if let Some(rest) = url.strip_prefix('[') {
+        if let Some(end) = rest.find(']') {
+            return &rest[..end];
+        }
+    }
+    match url.rfind(':') {
+        // More than one colon without brackets is a bare IPv6 address
+        Some(i) if url[..i].find(':').is_none() => &url[..i],
+        _ => url,
+    }
+}
+
+/// The name of this machine, as used in the names of its sources
+///
+/// This is the `COMPUTERNAME` or `HOSTNAME` environment variable, or the hostname of the system if neither is set,
+/// without any domain
+fn local_machine_name() -> Option<String> {
+    let hostname = ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|name| !name.trim().is_empty())
+        .or_else(system_hostname)?;
+    machine_from_hostname(&hostname).map(str::to_string)
+}
+
+/// Strip any domain from a hostname, eg `studio-pc.local`
+fn machine_from_hostname(hostname: &str) -> Option<&str> {
+    match hostname.trim().split('.').next() {
+        Some(machine) if !machine.is_empty() => Some(machine),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn system_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+/// Windows always sets `COMPUTERNAME`, so there is no fallback
+#[cfg(not(unix))]
+fn system_hostname() -> Option<String> {
+    None
+}
+
+/// The sources that changed between two calls to `FindInstance::get_sources_diff`
+#[derive(Debug, Clone, Default)]
+pub struct SourcesDiff {
+    pub added: Vec<FindSource>,
+    pub removed: Vec<FindSource>,
+}
 
 unsafe impl Send for FindInstance {}
 pub struct FindInstance {
     sdk_instance: Arc<NDIHandle>,
     instance: sdk::NDIlib_find_instance_t,
+    last_sources: Mutex<Vec<FindSource>>,
+    manual_sources: Mutex<Vec<FindSource>>,
+    poll_timeout: u32,
 }
 impl Drop for FindInstance {
     fn drop(&mut self) {
@@ -23,10 +165,64 @@ impl Drop for FindInstance {
     }
 }
 impl FindInstance {
+    /// The SDK handle of the finder. See the `raw` module for the safety rules
+    #[cfg(feature = "unsafe-raw")]
+    pub fn raw_instance(&self) -> sdk::NDIlib_find_instance_t {
+        self.instance
+    }
+
+    /// Get the sources found so far, followed by any added with `add_manual_source` which weren't found
     pub fn get_current_sources(&self) -> Vec<FindSource> {
+        let mut sources = self.discovered_sources();
+        if let Ok(manual) = self.manual_sources.lock() {
+            for source in manual.iter() {
+                let found = sources
+                    .iter()
+                    .any(|s| s == source || (s.url.is_some() && s.url == source.url));
+                if !found {
+                    sources.push(source.clone());
+                }
+            }
+        }
+        sources
+    }
+
+    /// Add a source at a known address, for networks where discovery is blocked
+    ///
+    /// The source is included in `get_current_sources` from then on, unless a source with the same name or address
+    /// is discovered, in which case the discovered one is used instead. Adding a source with the same name as an
+    /// earlier one replaces it. Adding a source doesn't wake `wait_for_sources`, so it shows up in
+    /// `get_sources_diff` after the next discovered change.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The full name of the source, eg `MACHINE (Source)`
+    /// * `url` - The address of the source, eg `192.168.1.5:5961`
+    ///
+    pub fn add_manual_source(&self, name: &str, url: &str) {
+        let source = FindSource::from_url(name, url);
+        if let Ok(mut manual) = self.manual_sources.lock() {
+            manual.retain(|s| *s != source);
+            manual.push(source);
+        }
+    }
+
+    /// Remove a source added with `add_manual_source`, returning whether there was one with the name
+    pub fn remove_manual_source(&self, name: &str) -> bool {
+        match self.manual_sources.lock() {
+            Ok(mut manual) => {
+                let count = manual.len();
+                manual.retain(|s| s.name != name);
+                manual.len() != count
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn discovered_sources(&self) -> Vec<FindSource> {
         unsafe {
             let mut source_count = 0;
-            // Memory is freed on next call, or destroy
+            // Memory is freed on next call, or destroy, so the strings are copied before returning
             let sources = self.sdk_instance.NDIlib_find_get_current_sources.unwrap()(self.instance, &mut source_count);
 
             slice::from_raw_parts(sources, source_count as usize)
@@ -48,23 +244,363 @@ impl FindInstance {
         }
     }
 
+    /// Get the current sources, leaving out any from the given machines
+    ///
+    /// Machine names are compared ignoring case. To leave out every source on the local machine, use
+    /// `get_current_sources_excluding_local`, or create the finder with `with_show_local_sources(false)`.
+    pub fn get_current_sources_excluding(&self, machines: &[&str]) -> Vec<FindSource> {
+        self.get_current_sources()
+            .into_iter()
+            .filter(|s| match s.machine_name() {
+                Some(machine) => !machines.iter().any(|m| m.eq_ignore_ascii_case(machine)),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Get the current sources, leaving out any on this machine
+    ///
+    /// The machine name is the `COMPUTERNAME` or `HOSTNAME` environment variable, or the hostname of the system
+    /// without its domain, and if none of these can be found every source is returned. Unlike creating the finder
+    /// with `with_show_local_sources(false)`, this can be decided per call.
+    pub fn get_current_sources_excluding_local(&self) -> Vec<FindSource> {
+        match local_machine_name() {
+            Some(machine) => self.get_current_sources_excluding(&[&machine]),
+            None => self.get_current_sources(),
+        }
+    }
+
+    /// Get the current sources, grouped by the machine they are on
+    ///
+    /// The machine is taken from names of the form `MACHINE (Source)`. Sources with any other name are grouped under
+    /// the host of their url instead, or under their full name if they have no url.
+    pub fn sources_by_machine(&self) -> HashMap<String, Vec<FindSource>> {
+        let mut machines: HashMap<String, Vec<FindSource>> = HashMap::new();
+        for source in self.get_current_sources() {
+            let machine = match (source.machine_name(), source.url_address()) {
+                (Some(machine), _) => machine.to_string(),
+                (None, Some(url)) => url_host(url).to_string(),
+                (None, None) => source.name.clone(),
+            };
+            machines.entry(machine).or_default().push(source);
+        }
+        machines
+    }
+
     pub fn wait_for_sources(&self, timeout: u32) -> bool {
         unsafe { self.sdk_instance.NDIlib_find_wait_for_sources.unwrap()(self.instance, timeout) }
     }
+
+    /// Wait for the list of sources to change, until a deadline
+    ///
+    /// # Returns
+    ///
+    /// True if the sources changed, or false if the deadline passed first
+    ///
+    pub fn wait_for_sources_until(&self, deadline: Instant) -> bool {
+        self.wait_for_sources_cancellable(deadline, &AtomicBool::new(false))
+    }
+
+    /// Wait for the list of sources to change, until a deadline or until `cancel` is set
+    ///
+    /// The SDK can't interrupt a wait, so this waits in slices of 50ms and checks `cancel` between them. This allows a
+    /// discovery thread to be shut down without waiting out a long timeout. Pass a deadline far in the future to wait
+    /// only for a change or cancellation.
+    ///
+    /// # Returns
+    ///
+    /// True if the sources changed, or false if the deadline passed or the wait was cancelled first
+    ///
+    pub fn wait_for_sources_cancellable(&self, deadline: Instant, cancel: &AtomicBool) -> bool {
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return false;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return false;
+            }
+            if self.wait_for_sources(remaining.min(CANCEL_CHECK_INTERVAL).as_millis() as u32) {
+                return true;
+            }
+        }
+    }
+
+    /// Wait for the list of sources to change, for up to the poll timeout set on the builder
+    ///
+    /// This is intended for loops which check `get_current_sources` after each wait
+    pub fn poll_sources(&self) -> bool {
+        self.wait_for_sources(self.poll_timeout)
+    }
+
+    /// The timeout used by `poll_sources`, in milliseconds
+    pub fn poll_timeout(&self) -> u32 {
+        self.poll_timeout
+    }
+
+    /// Wait for a source matching a predicate to be found
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait, in milliseconds
+    /// * `predicate` - Returns true for the wanted source
+    ///
+    /// # Returns
+    ///
+    /// The first matching source, or None if none was found before the timeout
+    ///
+    pub fn wait_for_source<F>(&self, timeout: u32, predicate: F) -> Option<FindSource>
+    where
+        F: Fn(&FindSource) -> bool,
+    {
+        let deadline = Instant::now() + Duration::from_millis(timeout as u64);
+        loop {
+            if let Some(source) = self.get_current_sources().into_iter().find(|s| predicate(s)) {
+                return Some(source);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return None;
+            }
+            self.wait_for_sources(remaining.as_millis() as u32);
+        }
+    }
+
+    /// Wait for the list of sources to change
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait, in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// The new list of sources, or None if nothing changed before the timeout
+    ///
+    pub fn get_sources_changed(&self, timeout: u32) -> Option<Vec<FindSource>> {
+        self.get_sources_diff(timeout)?;
+        self.last_sources.lock().ok().map(|s| s.clone())
+    }
+
+    /// Wait for the list of sources to change, and return which sources were added and removed since the last call
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait, in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// The changes, or None if nothing changed before the timeout
+    ///
+    pub fn get_sources_diff(&self, timeout: u32) -> Option<SourcesDiff> {
+        if !self.wait_for_sources(timeout) {
+            return None;
+        }
+
+        let current = self.get_current_sources();
+        let mut last = self.last_sources.lock().ok()?;
+
+        let diff = SourcesDiff {
+            added: current.iter().filter(|s| !last.contains(s)).cloned().collect(),
+            removed: last.iter().filter(|s| !current.contains(s)).cloned().collect(),
+        };
+        *last = current;
+
+        if diff.added.is_empty() && diff.removed.is_empty() {
+            None
+        } else {
+            Some(diff)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FindCreateError {
+    InvalidGroups,
+    InvalidExtraIps,
+    InvalidDiscoveryServer,
+    /// The loaded runtime is too old to support a discovery server
+    DiscoveryServerUnsupported,
+    Failed,
+}
+
+/// The environment variable checked for a discovery server when one is not set on the builder
+pub const DISCOVERY_SERVER_VAR: &str = "NDI_DISCOVERY_SERVER";
+
+/// The default timeout used by `FindInstance::poll_sources`, in milliseconds
+pub const DEFAULT_POLL_TIMEOUT: u32 = 1000;
+
+/// Options for creating a FindInstance
+pub struct FindInstanceBuilder {
+    sdk_instance: Arc<NDIHandle>,
+    show_local_sources: bool,
+    poll_timeout: u32,
+    groups: Option<String>,
+    extra_ips: Option<String>,
+    discovery_server: Option<String>,
+}
+impl FindInstanceBuilder {
+    /// Whether to include sources from the local machine. Defaults to true
+    ///
+    /// Turning this off stops a process which both sends and receives from connecting to itself
+    pub fn with_show_local_sources(mut self, show_local_sources: bool) -> Self {
+        self.show_local_sources = show_local_sources;
+        self
+    }
+    /// Only find sources in the given comma separated list of groups, eg `Studio A,Studio B`
+    pub fn with_groups(mut self, groups: String) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+    /// Also query the given comma separated list of IP addresses for sources, for networks without mDNS
+    pub fn with_extra_ips(mut self, extra_ips: String) -> Self {
+        self.extra_ips = Some(extra_ips);
+        self
+    }
+    /// Find sources using an NDI Discovery Server at the given address, eg `192.168.1.2`
+    ///
+    /// If this is not set, the address in the `NDI_DISCOVERY_SERVER` environment variable is used if present. An
+    /// invalid address, or a runtime without discovery server support, fails the build when the address was set
+    /// here, but when it came from the environment it is ignored with a warning and sources are found as usual
+    pub fn with_discovery_server(mut self, address: String) -> Self {
+        self.discovery_server = Some(address);
+        self
+    }
+    /// How long `FindInstance::poll_sources` waits for a change, in milliseconds. Defaults to
+    /// `DEFAULT_POLL_TIMEOUT`
+    pub fn with_poll_timeout(mut self, poll_timeout: u32) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+    pub fn build(self) -> Result<FindInstance, FindCreateError> {
+        let groups = match self.groups {
+            None => None,
+            Some(groups) => Some(CString::new(groups.as_bytes()).map_err(|_| FindCreateError::InvalidGroups)?),
+        };
+        let extra_ips = match self.extra_ips {
+            None => None,
+            Some(ips) => Some(CString::new(ips.as_bytes()).map_err(|_| FindCreateError::InvalidExtraIps)?),
+        };
+        let explicit = self.discovery_server.is_some();
+        let config = match &self.discovery_server {
+            Some(address) => Some(discovery_config(address).ok_or(FindCreateError::InvalidDiscoveryServer)?),
+            None => env_discovery_config(env::var(DISCOVERY_SERVER_VAR).ok().as_deref()),
+        };
+
+        let props = sdk::NDIlib_find_create_t {
+            show_local_sources: self.show_local_sources,
+            p_groups: groups.as_ref().map_or(null(), |g| g.as_ptr()),
+            p_extra_ips: extra_ips.as_ref().map_or(null(), |ips| ips.as_ptr()),
+        };
+
+        let instance = match (config, self.sdk_instance.NDIlib_find_create_v3) {
+            (Some(config), Some(create)) => unsafe { create(&props, config.as_ptr()) },
+            (Some(_), None) if explicit => return Err(FindCreateError::DiscoveryServerUnsupported),
+            (config, _) => {
+                if config.is_some() {
+                    ndi_warn!(
+                        "Ignoring {}, the NDI runtime does not support discovery servers",
+                        DISCOVERY_SERVER_VAR
+                    );
+                }
+                unsafe { self.sdk_instance.NDIlib_find_create_v2.unwrap()(&props) }
+            }
+        };
+
+        if instance.is_null() {
+            Err(FindCreateError::Failed)
+        } else {
+            Ok(FindInstance {
+                sdk_instance: self.sdk_instance,
+                instance,
+                last_sources: Mutex::new(Vec::new()),
+                manual_sources: Mutex::new(Vec::new()),
+                poll_timeout: self.poll_timeout,
+            })
+        }
+    }
+}
+
+pub fn create_find_builder(sdk_instance: Arc<NDIHandle>) -> FindInstanceBuilder {
+    FindInstanceBuilder {
+        sdk_instance,
+        show_local_sources: true,
+        poll_timeout: DEFAULT_POLL_TIMEOUT,
+        groups: None,
+        extra_ips: None,
+        discovery_server: None,
+    }
+}
+
+/// Build the SDK config JSON for using a discovery server
+fn discovery_config(address: &str) -> Option<CString> {
+    if address.is_empty() || address.contains(|c: char| c == '"' || c == '\\' || c.is_control()) {
+        return None;
+    }
+
+    CString::new(format!(r#"{{"ndi":{{"networks":{{"discovery":"{}"}}}}}}"#, address)).ok()
+}
+
+/// The config for the discovery server in the environment variable, if it is set
+///
+/// An empty value is treated as unset, and an invalid one is ignored with a warning, so that a bad variable doesn't
+/// stop every finder from being created
+fn env_discovery_config(value: Option<&str>) -> Option<CString> {
+    let address = value?.trim();
+    if address.is_empty() {
+        return None;
+    }
+
+    let config = discovery_config(address);
+    if config.is_none() {
+        ndi_warn!(
+            "Ignoring the invalid discovery server {:?} in {}",
+            address,
+            DISCOVERY_SERVER_VAR
+        );
+    }
+    config
 }
 
 pub fn create_find_instance(sdk_instance: Arc<NDIHandle>, show_local_sources: bool) -> Option<FindInstance> {
-    let props = sdk::NDIlib_find_create_t {
-        show_local_sources,
-        p_groups: null(),
-        p_extra_ips: null(),
-    };
-
-    let instance = unsafe { sdk_instance.NDIlib_find_create_v2.unwrap()(&props) };
-
-    if instance.is_null() {
-        None
-    } else {
-        Some(FindInstance { sdk_instance, instance })
+    create_find_builder(sdk_instance)
+        .with_show_local_sources(show_local_sources)
+        .build()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_names() {
+        assert_eq!(machine_from_hostname("STUDIO-PC"), Some("STUDIO-PC"));
+        assert_eq!(machine_from_hostname("studio-pc.local\n"), Some("studio-pc"));
+        assert_eq!(machine_from_hostname("studio-pc.example.com"), Some("studio-pc"));
+        assert_eq!(machine_from_hostname(""), None);
+        assert_eq!(machine_from_hostname(".local"), None);
+    }
+
+    #[test]
+    fn env_discovery_server() {
+        assert!(env_discovery_config(None).is_none());
+        assert!(env_discovery_config(Some("")).is_none());
+        assert!(env_discovery_config(Some(" \t")).is_none());
+        assert!(env_discovery_config(Some("bad\"address")).is_none());
+        let config = env_discovery_config(Some(" 192.168.1.2 ")).unwrap();
+        assert_eq!(
+            config.to_str().unwrap(),
+            r#"{"ndi":{"networks":{"discovery":"192.168.1.2"}}}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn local_machine() {
+        let hostname = system_hostname().unwrap();
+        assert!(!hostname.is_empty());
+        assert!(local_machine_name().is_some());
     }
 }
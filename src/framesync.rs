@@ -0,0 +1,182 @@
+use crate::receive::{
+    update_video_format, AudioFrame, FrameFormatType, FrameOwner, ReceiveDataStore, ReceiveInstance, VideoFormat,
+    VideoFrame,
+};
+use crate::sdk;
+use std::ptr::{null, null_mut};
+use std::sync::{Arc, Mutex};
+
+unsafe impl Send for FrameSyncInstance {}
+unsafe impl Sync for FrameSyncInstance {}
+/// A frame synchronizer attached to a receiver
+///
+/// This will always return the most recent video frame, and audio resampled to fill the requested number of samples,
+/// without blocking. This is the recommended way of receiving for playback, as it keeps audio and video in sync.
+///
+/// The receiver should not be used for capturing while a FrameSyncInstance exists for it.
+pub struct FrameSyncInstance {
+    receiver: Arc<ReceiveInstance>,
+    instance: sdk::NDIlib_framesync_instance_t,
+    video_frames: ReceiveDataStore<sdk::NDIlib_video_frame_v2_t>,
+    audio_frames: ReceiveDataStore<sdk::NDIlib_audio_frame_v2_t>,
+    video_format: Mutex<Option<VideoFormat>>,
+}
+impl Drop for FrameSyncInstance {
+    fn drop(&mut self) {
+        unsafe {
+            if let Ok(frame_store) = self.video_frames.data.lock() {
+                for f in frame_store.values() {
+                    self.free_video_inner(f)
+                }
+            }
+            if let Ok(frame_store) = self.audio_frames.data.lock() {
+                for f in frame_store.values() {
+                    self.free_audio_inner(f)
+                }
+            }
+
+            self.receiver.sdk_instance.NDIlib_framesync_destroy.unwrap()(self.instance);
+        }
+    }
+}
+impl FrameSyncInstance {
+    /// The format of the last video frame captured, or None if no video has been captured yet
+    ///
+    /// Compare this against each new frame with `VideoFrame::format_changed_since` to detect mode switches
+    pub fn last_video_format(&self) -> Option<VideoFormat> {
+        self.video_format.lock().ok().and_then(|f| *f)
+    }
+    fn free_video_inner(&self, video: &Arc<Mutex<sdk::NDIlib_video_frame_v2_t>>) {
+        if let Ok(mut ndi_ref) = video.lock() {
+            unsafe {
+                self.receiver.sdk_instance.NDIlib_framesync_free_video.unwrap()(self.instance, &mut *ndi_ref);
+                ndi_ref.p_data = null_mut();
+            }
+        }
+    }
+    fn free_audio_inner(&self, audio: &Arc<Mutex<sdk::NDIlib_audio_frame_v2_t>>) {
+        if let Ok(mut ndi_ref) = audio.lock() {
+            unsafe {
+                self.receiver.sdk_instance.NDIlib_framesync_free_audio.unwrap()(self.instance, &mut *ndi_ref);
+                ndi_ref.p_data = null_mut();
+            }
+        }
+    }
+}
+impl FrameOwner for FrameSyncInstance {
+    fn free_video(&self, id: usize) {
+        if let Some(frame) = self.video_frames.remove(id) {
+            self.free_video_inner(&frame);
+        }
+    }
+    fn free_audio(&self, id: usize) {
+        if let Some(frame) = self.audio_frames.remove(id) {
+            self.free_audio_inner(&frame);
+        }
+    }
+}
+
+pub trait FrameSyncInstanceExt {
+    /// Get the most recent video frame
+    ///
+    /// # Arguments
+    ///
+    /// * `field_type` The field type to return. Use `FrameFormatType::Progressive` for a full frame
+    ///
+    /// # Returns
+    ///
+    /// The frame, or None if no video has been received yet
+    ///
+    fn capture_video(&self, field_type: FrameFormatType) -> Option<VideoFrame>;
+
+    /// Get audio to fill the requested number of samples
+    ///
+    /// Any arguments can be 0 to use the values of the source. If no audio has been received yet, silence is returned
+    fn capture_audio(&self, sample_rate: i32, channel_count: i32, sample_count: i32) -> Option<AudioFrame>;
+}
+
+impl FrameSyncInstanceExt for Arc<FrameSyncInstance> {
+    fn capture_video(&self, field_type: FrameFormatType) -> Option<VideoFrame> {
+        let mut video_data = sdk::NDIlib_video_frame_v2_t {
+            xres: 0,
+            yres: 0,
+            FourCC: Default::default(),
+            frame_rate_N: 0,
+            frame_rate_D: 0,
+            picture_aspect_ratio: 0.0,
+            frame_format_type: Default::default(),
+            timecode: 0,
+            p_data: null_mut(),
+            line_stride_in_bytes: 0,
+            p_metadata: null(),
+            timestamp: 0,
+        };
+
+        unsafe {
+            self.receiver.sdk_instance.NDIlib_framesync_capture_video.unwrap()(
+                self.instance,
+                &mut video_data,
+                field_type as u32,
+            );
+        }
+
+        if video_data.p_data.is_null() {
+            unsafe {
+                self.receiver.sdk_instance.NDIlib_framesync_free_video.unwrap()(self.instance, &mut video_data);
+            }
+            None
+        } else {
+            let frame = VideoFrame::track(&self.video_frames, Arc::downgrade(self) as _, video_data).ok()?;
+            update_video_format(&self.video_format, frame.format());
+            Some(frame)
+        }
+    }
+
+    fn capture_audio(&self, sample_rate: i32, channel_count: i32, sample_count: i32) -> Option<AudioFrame> {
+        let mut audio_data = sdk::NDIlib_audio_frame_v2_t {
+            sample_rate: 0,
+            no_channels: 0,
+            no_samples: 0,
+            timecode: 0,
+            p_data: null_mut(),
+            channel_stride_in_bytes: 0,
+            p_metadata: null(),
+            timestamp: 0,
+        };
+
+        unsafe {
+            self.receiver.sdk_instance.NDIlib_framesync_capture_audio.unwrap()(
+                self.instance,
+                &mut audio_data,
+                sample_rate,
+                channel_count,
+                sample_count,
+            );
+        }
+
+        AudioFrame::track(&self.audio_frames, Arc::downgrade(self) as _, audio_data).ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameSyncCreateError {
+    Failed,
+}
+
+pub fn create_framesync_instance(
+    receiver: Arc<ReceiveInstance>,
+) -> Result<Arc<FrameSyncInstance>, FrameSyncCreateError> {
+    let instance = unsafe { receiver.sdk_instance.NDIlib_framesync_create.unwrap()(receiver.instance) };
+
+    if instance.is_null() {
+        Err(FrameSyncCreateError::Failed)
+    } else {
+        Ok(Arc::new(FrameSyncInstance {
+            receiver,
+            instance,
+            video_frames: ReceiveDataStore::new(),
+            audio_frames: ReceiveDataStore::new(),
+            video_format: Mutex::new(None),
+        }))
+    }
+}
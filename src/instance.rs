@@ -1,12 +1,100 @@
 pub use self::internal::{load, NDIHandle};
+use crate::{sdk, NDIInstance};
+use std::ffi::CStr;
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+
+/// Why the SDK could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The library could not be loaded, or isn't an NDI runtime, with a description of what was tried
+    Library(String),
+    /// The CPU doesn't support the instructions NDI needs, so NDI can't be used on this machine
+    UnsupportedCpu,
+    /// Initialising the SDK failed for another reason
+    InitFailed,
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Library(description) => f.write_str(description),
+            LoadError::UnsupportedCpu => {
+                f.write_str("Init failed, the CPU is not supported by NDI (SSE4.2 is required)")
+            }
+            LoadError::InitFailed => f.write_str("Init returned false"),
+        }
+    }
+}
+impl std::error::Error for LoadError {}
+
+/// The handle of the currently loaded SDK, so it is only initialised and destroyed once however many times `load()`
+/// is called
+static SHARED_HANDLE: Mutex<Option<Weak<NDIHandle>>> = Mutex::new(None);
+
+/// Reuse the loaded SDK if there is one, otherwise create it
+fn load_shared<F>(create: F) -> Result<NDIInstance, LoadError>
+where
+    F: FnOnce() -> Result<NDIInstance, LoadError>,
+{
+    let mut shared = SHARED_HANDLE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(handle) = shared.as_ref().and_then(|h| h.upgrade()) {
+        return Ok(NDIInstance { handle });
+    }
+
+    let instance = create().map_err(|e| {
+        ndi_warn!("Failed to load NDI: {}", e);
+        e
+    })?;
+    ndi_debug!("Loaded NDI {}", version_of(&instance.handle).unwrap_or_default());
+    *shared = Some(Arc::downgrade(&instance.handle));
+    Ok(instance)
+}
+
+/// Whether the CPU supports the instructions needed by NDI
+///
+/// When the feature 'dynamic-link' is enabled, the library is loaded from the default search paths, and this returns
+/// false if it can not be loaded. When this is false, `load()` will fail
+pub fn is_supported_cpu() -> bool {
+    internal::with_library(|inst| unsafe { inst.NDIlib_is_supported_CPU.unwrap()() }).unwrap_or(false)
+}
+
+/// The version of the NDI runtime, eg for logging
+///
+/// When the feature 'dynamic-link' is enabled, the library is loaded from the default search paths, so use
+/// `NDIInstance::version()` to get the version of a library loaded from a custom path
+pub fn version() -> Option<String> {
+    internal::with_library(version_of).flatten()
+}
+
+pub(crate) fn version_of(inst: &sdk::NDIlib_v3) -> Option<String> {
+    unsafe {
+        let version = inst.NDIlib_version.unwrap()();
+        if version.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(version).to_string_lossy().into_owned())
+        }
+    }
+}
+
+/// Find out why `NDIlib_initialize` failed
+fn init_error(inst: &sdk::NDIlib_v3) -> LoadError {
+    if unsafe { inst.NDIlib_is_supported_CPU.unwrap()() } {
+        LoadError::InitFailed
+    } else {
+        LoadError::UnsupportedCpu
+    }
+}
 
 #[cfg(not(feature = "dynamic-link"))]
 mod internal {
+    use super::LoadError;
     use crate::{sdk, NDIInstance};
     use std::ops::Deref;
     use std::sync::Arc;
 
     unsafe impl Send for NDIHandle {}
+    unsafe impl Sync for NDIHandle {}
     pub struct NDIHandle {
         instance: sdk::NDIlib_v3,
     }
@@ -29,32 +117,43 @@ mod internal {
     ///
     /// # Arguments
     ///
-    /// When the feature 'dynamic-link' is enabled, this method has a different signature of `pub fn load(custom_path: Option<String>) -> Result<NDIInstance, String>`
+    /// When the feature 'dynamic-link' is enabled, this method has a different signature of `pub fn load(custom_path: Option<String>) -> Result<NDIInstance, LoadError>`
     /// This allows for loading from a custom path
     ///
     /// # Returns
     ///
-    /// Returns a NDIInstance when successful.
+    /// Returns a NDIInstance when successful, or `LoadError::UnsupportedCpu` if the CPU can't run NDI. If the SDK is
+    /// already loaded, the same instance is returned
     ///
-    pub fn load() -> Result<NDIInstance, String> {
+    pub fn load() -> Result<NDIInstance, LoadError> {
+        super::load_shared(load_new)
+    }
+
+    fn load_new() -> Result<NDIInstance, LoadError> {
         let instance = unsafe { sdk::NDIlib_v3_load().as_ref() };
         match instance {
-            None => Err("Failed to load lib".to_string()),
+            None => Err(LoadError::Library("Failed to load lib".to_string())),
             Some(inst) => {
                 if unsafe { inst.NDIlib_initialize.unwrap()() } {
                     Ok(NDIInstance {
                         handle: Arc::new(NDIHandle { instance: *inst }),
                     })
                 } else {
-                    Err("Init returned false".to_string())
+                    Err(super::init_error(inst))
                 }
             }
         }
     }
+
+    /// Run a function with the function table of the library, without initialising it
+    pub(super) fn with_library<T, F: FnOnce(&sdk::NDIlib_v3) -> T>(f: F) -> Option<T> {
+        unsafe { sdk::NDIlib_v3_load().as_ref() }.map(f)
+    }
 }
 
 #[cfg(feature = "dynamic-link")]
 mod internal {
+    use super::LoadError;
     use crate::{sdk, NDIInstance};
     use libloading::{Library, Symbol};
     use std::env;
@@ -63,6 +162,7 @@ mod internal {
     use std::sync::Arc;
 
     unsafe impl Send for NDIHandle {}
+    unsafe impl Sync for NDIHandle {}
     pub struct NDIHandle {
         _handle: Option<Library>,
         instance: sdk::NDIlib_v3,
@@ -82,50 +182,131 @@ mod internal {
         }
     }
 
-    pub fn load(custom_path: Option<String>) -> Result<NDIInstance, String> {
-        let lib_path = if let Some(path) = custom_path {
-            path
-        } else {
-            let local_path = "./libndi.so";
-            if Path::new(local_path).exists() {
-                local_path.to_string()
-            } else if let Ok(env_var) = env::var("NDI_RUNTIME_DIR_V3") {
-                let p = Path::new(&env_var).join("libndi.so");
+    #[cfg(target_os = "windows")]
+    const LIBRARY_NAMES: &[&str] = &["Processing.NDI.Lib.x64.dll"];
+    #[cfg(target_os = "macos")]
+    const LIBRARY_NAMES: &[&str] = &["libndi.dylib"];
+    /// Newer runtimes are versioned differently, so the versioned names are tried newest first
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    const LIBRARY_NAMES: &[&str] = &["libndi.so.6", "libndi.so.5", "libndi.so.4", "libndi.so.3", "libndi.so"];
+
+    /// The environment variables set by the NDI runtime installers, newest first
+    const RUNTIME_DIR_VARS: [&str; 4] = [
+        "NDI_RUNTIME_DIR_V6",
+        "NDI_RUNTIME_DIR_V5",
+        "NDI_RUNTIME_DIR_V4",
+        "NDI_RUNTIME_DIR_V3",
+    ];
+
+    fn candidate_paths(custom_path: Option<String>) -> Vec<String> {
+        if let Some(path) = custom_path {
+            return vec![path];
+        }
+
+        let mut dirs = vec![".".to_string()];
+        dirs.extend(RUNTIME_DIR_VARS.iter().filter_map(|var| env::var(var).ok()));
+
+        let mut paths = Vec::new();
+        for dir in &dirs {
+            for name in LIBRARY_NAMES {
+                let p = Path::new(dir).join(name);
                 if p.exists() {
-                    p.to_str().unwrap_or("libndi.so").to_string()
-                } else {
-                    "libndi.so".to_string()
+                    paths.push(p.to_string_lossy().into_owned());
                 }
-            } else {
-                "libndi.so".to_string()
             }
-        };
-
-        match Library::new(lib_path) {
-            Err(e) => Err(format!("Failed to load lib: {}", e)),
-            Ok(lib) => unsafe {
-                let symbol: std::io::Result<Symbol<fn() -> *const sdk::NDIlib_v3>> = lib.get(b"NDIlib_v3_load");
-                match symbol {
-                    Err(e) => Err(format!("Invalid lib: {}", e)),
-                    Ok(s) => {
-                        let instance = s();
-                        if instance.is_null() {
-                            Err("Library failed to initialise".to_string())
+        }
+
+        // Fall back to the system search paths
+        paths.extend(LIBRARY_NAMES.iter().map(|name| name.to_string()));
+
+        paths
+    }
+
+    fn load_library(lib: Library) -> Result<NDIInstance, LoadError> {
+        unsafe {
+            let symbol: std::io::Result<Symbol<unsafe extern "C" fn() -> *const sdk::NDIlib_v3>> =
+                lib.get(b"NDIlib_v3_load");
+            match symbol {
+                Err(e) => Err(LoadError::Library(format!("Invalid lib: {}", e))),
+                Ok(s) => match s().as_ref() {
+                    None => Err(LoadError::Library("Library failed to initialise".to_string())),
+                    Some(inst) => {
+                        if inst.NDIlib_initialize.unwrap()() {
+                            Ok(NDIInstance {
+                                handle: Arc::new(NDIHandle {
+                                    instance: *inst,
+                                    _handle: Some(lib),
+                                }),
+                            })
                         } else {
-                            if inst.NDIlib_initialize.unwrap()() {
-                                Ok(NDIInstance {
-                                    handle: Arc::new(NDIHandle {
-                                        _handle: Some(lib),
-                                        instance: *instance,
-                                    }),
-                                })
-                            } else {
-                                Err("Init returned false".to_string())
-                            }
+                            Err(super::init_error(inst))
                         }
                     }
+                },
+            }
+        }
+    }
+
+    /// Initialise an instance of the SDK, loading the library at runtime
+    ///
+    /// # Arguments
+    ///
+    /// * `custom_path` The path to load the library from. When None, the current directory, then the runtime
+    ///   directories in the `NDI_RUNTIME_DIR_V6` to `NDI_RUNTIME_DIR_V3` environment variables, newest first, then
+    ///   the system search paths are tried. On Linux each place is searched for `libndi.so.6` down to `libndi.so.3`,
+    ///   then `libndi.so`
+    ///
+    /// # Returns
+    ///
+    /// Returns a NDIInstance when successful, `LoadError::Library` describing why the library could not be loaded, or
+    /// `LoadError::UnsupportedCpu` if the CPU can't run NDI. If the SDK is already loaded, the same instance is
+    /// returned and `custom_path` is ignored
+    ///
+    pub fn load(custom_path: Option<String>) -> Result<NDIInstance, LoadError> {
+        super::load_shared(|| load_new(custom_path))
+    }
+
+    fn load_new(custom_path: Option<String>) -> Result<NDIInstance, LoadError> {
+        let mut errors = Vec::new();
+        for path in candidate_paths(custom_path) {
+            match Library::new(&path) {
+                Err(e) => errors.push(format!("{}: {}", path, e)),
+                Ok(lib) => return load_library(lib),
+            }
+        }
+
+        Err(LoadError::Library(format!(
+            "Failed to load lib, is the NDI runtime installed? ({})",
+            errors.join(", ")
+        )))
+    }
+
+    /// Run a function with the function table of the library from the default search paths, without initialising it
+    pub(super) fn with_library<T, F: FnOnce(&sdk::NDIlib_v3) -> T>(f: F) -> Option<T> {
+        for path in candidate_paths(None) {
+            if let Ok(lib) = Library::new(&path) {
+                unsafe {
+                    let symbol: std::io::Result<Symbol<unsafe extern "C" fn() -> *const sdk::NDIlib_v3>> =
+                        lib.get(b"NDIlib_v3_load");
+                    return symbol.ok().and_then(|s| s().as_ref()).map(f);
                 }
-            },
+            }
         }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_error_messages() {
+        assert_eq!(
+            LoadError::Library("Failed to load lib".to_string()).to_string(),
+            "Failed to load lib"
+        );
+        assert!(LoadError::UnsupportedCpu.to_string().contains("SSE4.2"));
     }
 }
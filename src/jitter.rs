@@ -0,0 +1,115 @@
+//! Smoothing out network jitter by playing video frames back on a fixed latency
+
+use crate::receive::{VideoFrame, TIMESTAMP_UNDEFINED};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How far a frame can be from its expected time before the buffer resynchronises to the sender, in 100ns units
+const RESYNC_THRESHOLD: i64 = 10_000_000;
+
+/// Holds video frames and releases each one a fixed latency after it was sent
+///
+/// Frames are ordered by their timestamp, falling back to the timecode for senders which don't provide one. The
+/// sender's clock is mapped onto `Instant` using the first frame, and remapped whenever a frame arrives more than a
+/// second from where it was expected, eg after the source restarts.
+///
+/// Buffered frames still hold their SDK buffers, so the latency should be kept to a few frames.
+pub struct JitterBuffer {
+    latency: Duration,
+    max_frames: usize,
+    frames: VecDeque<(i64, VideoFrame)>,
+    base: Option<(Instant, i64)>,
+}
+impl JitterBuffer {
+    /// Create a buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - How long after a frame was sent it should be shown
+    /// * `max_frames` - The most frames to hold. When full the oldest frame is dropped
+    ///
+    pub fn new(latency: Duration, max_frames: usize) -> JitterBuffer {
+        JitterBuffer {
+            latency,
+            max_frames: max_frames.max(1),
+            frames: VecDeque::with_capacity(max_frames),
+            base: None,
+        }
+    }
+
+    fn frame_time(frame: &VideoFrame) -> i64 {
+        if frame.timestamp == TIMESTAMP_UNDEFINED {
+            frame.timecode
+        } else {
+            frame.timestamp
+        }
+    }
+
+    /// When a frame sent at `time` should be released
+    fn due_at(&self, base: (Instant, i64), time: i64) -> Option<Instant> {
+        let offset = time - base.1;
+        let sent = if offset < 0 {
+            base.0.checked_sub(Duration::from_nanos(offset.unsigned_abs() * 100))?
+        } else {
+            base.0 + Duration::from_nanos(offset as u64 * 100)
+        };
+        Some(sent + self.latency)
+    }
+
+    /// Add a received frame to the buffer
+    pub fn push(&mut self, frame: VideoFrame) {
+        let now = Instant::now();
+        let time = Self::frame_time(&frame);
+
+        // Resynchronise if the frame is far from where the clock mapping expects it
+        let in_sync = self.base.is_some_and(|base| {
+            let expected = now.saturating_duration_since(base.0).as_nanos() as i64 / 100 + base.1;
+            (time - expected).abs() <= RESYNC_THRESHOLD
+        });
+        if !in_sync {
+            self.base = Some((now, time));
+        }
+
+        let index = self
+            .frames
+            .iter()
+            .position(|(t, _)| *t > time)
+            .unwrap_or(self.frames.len());
+        self.frames.insert(index, (time, frame));
+
+        while self.frames.len() > self.max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Take the latest frame that is due by `now`
+    ///
+    /// Any earlier frames that are also due are dropped, so calling this once per display refresh always shows the
+    /// most recent frame without building up latency.
+    pub fn pop_due(&mut self, now: Instant) -> Option<VideoFrame> {
+        let base = self.base?;
+        let mut res = None;
+        while let Some((time, _)) = self.frames.front() {
+            match self.due_at(base, *time) {
+                Some(due) if due > now => break,
+                _ => res = self.frames.pop_front().map(|(_, frame)| frame),
+            }
+        }
+        res
+    }
+
+    /// The number of frames currently held
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Drop all held frames, eg when switching sources
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.base = None;
+    }
+}
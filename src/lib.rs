@@ -1,25 +1,134 @@
-use crate::finder::FindInstance;
+use crate::finder::{FindInstance, FindInstanceBuilder};
 use crate::instance::NDIHandle;
+use crate::pattern::TestPattern;
 use std::sync::Arc;
 
+#[cfg(not(feature = "bindgen"))]
 #[allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code, clippy::all)]
 mod sdk;
+#[cfg(feature = "bindgen")]
+#[allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code, clippy::all)]
+mod sdk {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+#[macro_use]
+mod diagnostics;
+
+/// The raw FFI bindings, for calling SDK functions which this crate doesn't wrap yet
+///
+/// The function table is available from `NDIInstance::raw_library()`, and the SDK handles from the `raw_instance()`
+/// method of each wrapper. Mixing raw and safe calls is sound as long as:
+///
+/// * Handles are never destroyed through the raw API, as the wrappers destroy them when dropped
+/// * Handles are not used after the wrapper they came from has been dropped
+/// * Frames captured through the raw API are freed through the raw API, as the wrappers don't track them
+/// * The SDK's own threading rules are followed, eg a finder must not be used from two threads at once
+#[cfg(feature = "unsafe-raw")]
+pub mod raw {
+    pub use crate::sdk::*;
+}
 
+pub mod audio;
+pub mod convert;
+pub mod deinterlace;
 pub mod finder;
+pub mod framesync;
 mod instance;
+pub mod jitter;
+pub mod pattern;
+pub mod pool;
 pub mod receive;
+pub mod reconnect;
+pub mod record;
+pub mod routing;
 pub mod send;
 mod util;
 
-pub use crate::instance::load;
-use crate::receive::{ReceiveBandwidth, ReceiveColorFormat, ReceiveCreateError, ReceiveInstance};
-use crate::send::{SendCreateError, SendInstance};
+pub use crate::instance::{is_supported_cpu, load, version, LoadError};
+use crate::receive::{
+    ReceiveBandwidth, ReceiveCaptureError, ReceiveColorFormat, ReceiveCreateError, ReceiveInstance,
+    ReceiveInstanceBuilder,
+};
+use crate::routing::{RoutingCreateError, RoutingInstance};
+use crate::send::{SendCreateError, SendInstance, SendInstanceBuilder};
+
+/// An error from an operation on a connection
+#[derive(Debug)]
+pub enum NdiError {
+    /// The receiver is not connected to a source
+    NotConnected,
+    /// The source could not be passed to the SDK
+    InvalidSource,
+    /// The metadata could not be passed to the SDK
+    InvalidMetadata,
+    /// The SDK call reported a failure
+    SdkCall,
+    /// Capturing a frame failed
+    Capture(ReceiveCaptureError),
+    /// The receiver reported an error while capturing, usually because the connection to the source was lost
+    ConnectionLost,
+    /// No matching source was found before the timeout
+    SourceNotFound,
+    /// An instance could not be created
+    CreateFailed,
+    /// The SDK could not be loaded
+    Load(LoadError),
+    /// A buffer was too small for the data, which needed the given number of bytes
+    BufferTooSmall(usize),
+}
+
+impl From<ReceiveCaptureError> for NdiError {
+    fn from(err: ReceiveCaptureError) -> NdiError {
+        NdiError::Capture(err)
+    }
+}
+
+impl From<()> for NdiError {
+    fn from(_err: ()) -> NdiError {
+        NdiError::Capture(ReceiveCaptureError::Invalid)
+    }
+}
+
+/// Load the SDK, find the first available source, and create a receiver connected to it
+///
+/// See `NDIInstance::connect_to_first_source` for details. The SDK is kept loaded for as long as the receiver exists.
+pub fn connect_to_first_source(
+    bandwidth: ReceiveBandwidth,
+    color_format: ReceiveColorFormat,
+    timeout: u32,
+) -> Result<Arc<ReceiveInstance>, NdiError> {
+    #[cfg(not(feature = "dynamic-link"))]
+    let instance = load().map_err(NdiError::Load)?;
+    #[cfg(feature = "dynamic-link")]
+    let instance = load(None).map_err(NdiError::Load)?;
+
+    instance.connect_to_first_source(bandwidth, color_format, timeout)
+}
 
 /// A loaded SDK Instance
+///
+/// The SDK stays loaded until this and every finder, receiver and sender created from it have been dropped, as they
+/// each hold a reference to it. `NDIlib_destroy` is then called exactly once.
+///
+/// There is no other global state, so any number of finders, receivers and senders can be created from one instance.
+/// Receivers are `Send` and `Sync`, so each can capture on its own thread, while a `FindInstance` is only `Send` and
+/// so should be owned by one thread, handing out cloned `FindSource`s to the others.
 pub struct NDIInstance {
     handle: Arc<NDIHandle>,
 }
 impl NDIInstance {
+    /// The version of the loaded NDI runtime, eg for logging
+    pub fn version(&self) -> Option<String> {
+        instance::version_of(&self.handle)
+    }
+
+    /// The SDK function table, for calling functions which aren't wrapped. See the `raw` module for the safety rules
+    #[cfg(feature = "unsafe-raw")]
+    pub fn raw_library(&self) -> &raw::NDIlib_v3 {
+        &self.handle
+    }
+
     /// Initialise an instance of the NDI source finder
     ///
     /// # Arguments
@@ -34,6 +143,11 @@ impl NDIInstance {
         finder::create_find_instance(self.handle.clone(), show_local_sources)
     }
 
+    /// Create a builder for an instance of the NDI source finder, for setting options such as groups
+    pub fn create_find_builder(&self) -> FindInstanceBuilder {
+        finder::create_find_builder(self.handle.clone())
+    }
+
     /// Initialise an instance of the NDI receiver
     pub fn create_receive_instance(
         &self,
@@ -43,13 +157,106 @@ impl NDIInstance {
         receive::create_receive_instance(self.handle.clone(), bandwidth, color_format)
     }
 
+    /// Create a builder for an instance of the NDI receiver, for setting options such as the receiver name
+    pub fn create_receive_builder(&self) -> ReceiveInstanceBuilder {
+        receive::create_receive_builder(self.handle.clone())
+    }
+
+    /// Find the first available source, and create a receiver connected to it
+    ///
+    /// # Arguments
+    ///
+    /// * `bandwidth` The bandwidth to receive at
+    /// * `color_format` The color format to receive video in
+    /// * `timeout` How long to wait for a source to be found, in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// The connected receiver, or `NdiError::SourceNotFound` if no source was found before the timeout
+    ///
+    pub fn connect_to_first_source(
+        &self,
+        bandwidth: ReceiveBandwidth,
+        color_format: ReceiveColorFormat,
+        timeout: u32,
+    ) -> Result<Arc<ReceiveInstance>, NdiError> {
+        let finder = self.create_find_instance(true).ok_or(NdiError::CreateFailed)?;
+        let source = finder
+            .wait_for_source(timeout, |_| true)
+            .ok_or(NdiError::SourceNotFound)?;
+
+        let receiver = self
+            .create_receive_instance(bandwidth, color_format)
+            .map_err(|_| NdiError::CreateFailed)?;
+        receiver.connect(Some(&source))?;
+
+        Ok(receiver)
+    }
+
     /// Initialise an instance of the NDI sender
+    ///
+    /// # Arguments
+    ///
+    /// * `name` The name of the source, as it will appear to receivers
+    /// * `groups` An optional comma separated list of groups to advertise the source in
+    /// * `clock_video` Whether video sends should be clocked to the frame rate
+    /// * `clock_audio` Whether audio sends should be clocked to the sample rate
+    ///
     pub fn create_send_instance(
         &self,
         name: String,
+        groups: Option<String>,
         clock_video: bool,
         clock_audio: bool,
     ) -> Result<SendInstance, SendCreateError> {
-        send::create_send_instance(self.handle.clone(), name, clock_video, clock_audio)
+        send::create_send_instance(self.handle.clone(), name, groups, clock_video, clock_audio)
+    }
+
+    /// Create a builder for an NDI sender, for setting options such as clocking
+    ///
+    /// # Arguments
+    ///
+    /// * `name` The name of the source, as it will appear on the network
+    ///
+    pub fn create_send_builder(&self, name: String) -> SendInstanceBuilder {
+        send::create_send_builder(self.handle.clone(), name)
+    }
+
+    /// Create a routing source, which forwards other sources to its receivers under its own name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` The name of the routing source
+    /// * `groups` The comma separated groups to advertise the source in, or None for the default group
+    ///
+    pub fn create_routing_instance(
+        &self,
+        name: String,
+        groups: Option<String>,
+    ) -> Result<RoutingInstance, RoutingCreateError> {
+        routing::create_routing_instance(self.handle.clone(), name, groups)
+    }
+
+    /// Create a source which sends color bars with a moving line, for testing receivers without a real source
+    ///
+    /// # Arguments
+    ///
+    /// * `name` The name of the source
+    /// * `width` The width of the video, in pixels
+    /// * `height` The height of the video, in pixels
+    /// * `fps` The frame rate to send at
+    ///
+    /// # Returns
+    ///
+    /// The running test pattern, which stops sending when dropped
+    ///
+    pub fn create_test_pattern(
+        &self,
+        name: String,
+        width: i32,
+        height: i32,
+        fps: i32,
+    ) -> Result<TestPattern, SendCreateError> {
+        pattern::create_test_pattern(self.handle.clone(), name, width, height, fps)
     }
 }
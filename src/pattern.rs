@@ -0,0 +1,105 @@
+//! A sender which generates a test pattern, for testing receivers without a real source
+
+use crate::send::{
+    create_ndi_send_video_frame, create_send_instance, FrameFormatType, SendColorFormat, SendCreateError,
+};
+use crate::NDIHandle;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// The 75% SMPTE color bars, as BGRX: white, yellow, cyan, green, magenta, red and blue
+const BARS: [[u8; 4]; 7] = [
+    [191, 191, 191, 255],
+    [0, 191, 191, 255],
+    [191, 191, 0, 255],
+    [0, 191, 0, 255],
+    [191, 0, 191, 255],
+    [0, 0, 191, 255],
+    [191, 0, 0, 255],
+];
+
+/// A source sending color bars with a moving white line, from a background thread
+///
+/// Frames are clocked by the SDK at the requested frame rate. The source stops and is removed from the network when
+/// this is dropped, or if a frame fails to build, which `is_running` reports.
+pub struct TestPattern {
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+impl TestPattern {
+    /// Whether the pattern is still being sent
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+}
+impl Drop for TestPattern {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Draw the bars for every row of a BGRX frame
+fn draw_bars(width: usize, height: usize) -> Vec<u8> {
+    let mut row = Vec::with_capacity(width * 4);
+    for x in 0..width {
+        row.extend_from_slice(&BARS[x * BARS.len() / width]);
+    }
+    row.repeat(height)
+}
+
+pub fn create_test_pattern(
+    sdk_instance: Arc<NDIHandle>,
+    name: String,
+    width: i32,
+    height: i32,
+    fps: i32,
+) -> Result<TestPattern, SendCreateError> {
+    if width <= 0 || height <= 0 || fps <= 0 {
+        return Err(SendCreateError::InvalidFormat);
+    }
+
+    let mut sender = create_send_instance(sdk_instance, name, None, true, false)?;
+    let stopped = Arc::new(AtomicBool::new(false));
+    let thread_stopped = stopped.clone();
+
+    let thread = thread::spawn(move || {
+        let (width, height) = (width as usize, height as usize);
+        let bars = draw_bars(width, height);
+        let line_width = (width / 64).max(1);
+
+        let mut frame_no = 0;
+        while !thread_stopped.load(Ordering::Relaxed) {
+            // Move the line across the frame once every 2 seconds
+            let offset = frame_no * width / (fps as usize * 2) % width;
+            let mut data = bars.clone();
+            for row in data.chunks_exact_mut(width * 4) {
+                for x in offset..(offset + line_width).min(width) {
+                    row[x * 4..x * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+                }
+            }
+
+            let frame = create_ndi_send_video_frame(width as i32, height as i32, FrameFormatType::Progressive)
+                .with_framerate(fps, 1)
+                .with_data(data, width as i32 * 4, SendColorFormat::Bgrx)
+                .build();
+            match frame {
+                Ok(frame) => sender.send_video(frame),
+                Err(e) => {
+                    ndi_warn!("Stopping test pattern, failed to build a frame: {:?}", e);
+                    return;
+                }
+            }
+
+            frame_no += 1;
+        }
+    });
+
+    Ok(TestPattern {
+        stopped,
+        thread: Some(thread),
+    })
+}
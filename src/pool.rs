@@ -0,0 +1,100 @@
+use crate::receive::{ReceiveCaptureResult, ReceiveInstance, ReceiveInstanceExt, VideoFrame};
+use crate::NdiError;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, Weak};
+
+type BufferList = Mutex<Vec<Vec<u8>>>;
+
+/// A pool of RGBA conversion buffers for captured video frames
+///
+/// Buffers are handed back to the pool when a `PooledVideoFrame` is dropped, so once the pool has warmed up,
+/// capturing and converting frames does not allocate.
+pub struct FramePool {
+    buffers: Arc<BufferList>,
+    max_buffers: usize,
+}
+impl FramePool {
+    /// Create a pool which keeps up to `max_buffers` spare buffers
+    pub fn new(max_buffers: usize) -> FramePool {
+        FramePool {
+            buffers: Arc::new(Mutex::new(Vec::with_capacity(max_buffers))),
+            max_buffers,
+        }
+    }
+
+    /// Capture a video frame from a receiver
+    ///
+    /// # Returns
+    ///
+    /// The frame, or None if no video frame arrived before the timeout
+    ///
+    pub fn capture(&self, receiver: &Arc<ReceiveInstance>, timeout: u32) -> Result<Option<PooledVideoFrame>, NdiError> {
+        match receiver.receive_capture(true, false, false, timeout)? {
+            ReceiveCaptureResult::Video(frame) => Ok(Some(self.wrap(frame))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Attach a buffer from the pool to an already captured frame
+    pub fn wrap(&self, frame: VideoFrame) -> PooledVideoFrame {
+        let buffer = self.buffers.lock().ok().and_then(|mut b| b.pop()).unwrap_or_default();
+
+        PooledVideoFrame {
+            frame,
+            buffer,
+            converted: false,
+            pool: Arc::downgrade(&self.buffers),
+            max_buffers: self.max_buffers,
+        }
+    }
+
+    /// The number of spare buffers currently in the pool
+    pub fn available(&self) -> usize {
+        self.buffers.lock().map(|b| b.len()).unwrap_or(0)
+    }
+}
+
+/// A video frame with a conversion buffer borrowed from a `FramePool`
+pub struct PooledVideoFrame {
+    frame: VideoFrame,
+    buffer: Vec<u8>,
+    converted: bool,
+    pool: Weak<BufferList>,
+    max_buffers: usize,
+}
+impl Drop for PooledVideoFrame {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.upgrade() {
+            if let Ok(mut buffers) = pool.lock() {
+                if buffers.len() < self.max_buffers {
+                    buffers.push(std::mem::take(&mut self.buffer));
+                }
+            }
+        }
+    }
+}
+impl Deref for PooledVideoFrame {
+    type Target = VideoFrame;
+
+    fn deref(&self) -> &VideoFrame {
+        &self.frame
+    }
+}
+impl PooledVideoFrame {
+    /// Get the frame as tightly packed RGBA, converting it into the pooled buffer on first use
+    ///
+    /// Returns None if the data could not be converted
+    pub fn rgba(&mut self) -> Option<&[u8]> {
+        if !self.converted {
+            let len = self.frame.width as usize * self.frame.height as usize * 4;
+            self.buffer.resize(len, 0);
+            self.converted = self.frame.to_rgba_into(&mut self.buffer);
+        }
+
+        if self.converted {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+}
@@ -1,12 +1,25 @@
-use crate::finder::FindSource;
-use crate::util::to_ndi_source;
-use crate::{sdk, NDIHandle};
+use crate::audio::AudioBuffer;
+use crate::convert::{self, frame_data_len, ColorMatrix, I420Planes, RgbaFrame};
+use crate::finder::{create_find_instance, FindSource};
+use crate::framesync::{create_framesync_instance, FrameSyncCreateError, FrameSyncInstance};
+use crate::reconnect::{spawn_reconnect, ReconnectBackoff, ReconnectEvent, ReconnectHandle, ReconnectSource};
+use crate::record::{record, FrameSink, RecordError};
+use crate::send::TIMECODE_SYNTHESIZE;
+use crate::util::{
+    from_ndi_metadata, hash_bytes, interleave_audio, to_ndi_metadata, to_ndi_source, xml_attribute, xml_element,
+};
+use crate::{sdk, NDIHandle, NdiError};
 use ptrplus::AsPtr;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::ops::Deref;
 use std::ptr::{null, null_mut};
 use std::slice;
+#[cfg(feature = "async")]
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
@@ -22,17 +35,47 @@ impl<'a, T, T2> Deref for GuardedPointer<'a, T, T2> {
     }
 }
 
+/// The value of a frame timestamp when the sender did not provide one
+pub const TIMESTAMP_UNDEFINED: i64 = sdk::NDIlib_recv_timestamp_undefined;
+
+/// The data of a video frame, borrowed from it by `VideoFrame::lock_data`
+///
+/// The guard borrows the frame, so the borrow checker rejects dropping the frame, or moving it to another thread,
+/// while the data is still in use. The frame stays locked until the guard is dropped, so frames which are only read
+/// through the guard can't be freed underneath it, even by the receiver that captured them being dropped.
+///
+/// ```compile_fail,E0505
+/// fn use_after_free(frame: ndi_sdk::receive::VideoFrame) {
+///     let data = frame.lock_data();
+///     drop(frame);
+///     data.map(|d| d.len());
+/// }
+/// ```
 pub type VideoFrameData<'a> = GuardedPointer<'a, sdk::NDIlib_video_frame_v2_t, u8>;
 unsafe impl Send for VideoFrame {}
 unsafe impl Sync for VideoFrame {}
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum FrameFormatType {
+    /// A progressive frame
     Progressive = sdk::NDIlib_frame_format_type_progressive as isize,
+    /// An interlaced frame, with both fields interleaved line by line
     Interlaced = sdk::NDIlib_frame_format_type_interleaved as isize,
+    /// A single field containing the even lines of a frame, at half the frame height
+    ///
+    /// Fields are only received when the receiver was created with `with_allow_video_fields(true)`
     Field0 = sdk::NDIlib_frame_format_type_field_0 as isize,
+    /// A single field containing the odd lines of a frame, at half the frame height
+    ///
+    /// Fields are only received when the receiver was created with `with_allow_video_fields(true)`
     Field1 = sdk::NDIlib_frame_format_type_field_1 as isize,
 }
+impl FrameFormatType {
+    /// Whether this is a single field rather than a full frame
+    pub fn is_field(&self) -> bool {
+        matches!(self, FrameFormatType::Field0 | FrameFormatType::Field1)
+    }
+}
 
 impl TryFrom<u32> for FrameFormatType {
     type Error = ();
@@ -48,21 +91,47 @@ impl TryFrom<u32> for FrameFormatType {
     }
 }
 
+/// The pixel layout of video frame data
+///
+/// The byte orders below are the order of the bytes in memory, regardless of the endianness of the machine. So RGBA
+/// data can be uploaded directly as an `Rgba8Unorm` texture, and BGRA as `Bgra8Unorm`.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum FourCCType {
+    /// 4:2:2 YUV, with each pair of pixels stored as the bytes U, Y0, V, Y1
     UYVY = sdk::NDIlib_FourCC_type_UYVY as isize,
+    /// UYVY followed by a plane of one alpha byte per pixel, with a stride of the width
+    ///
+    /// This is only received with `ReceiveColorFormat::Fastest`, from sources with alpha. `VideoFrame::to_rgba`
+    /// combines the two planes into RGBA for keying.
     UYVA = sdk::NDIlib_FourCC_type_UYVA as isize,
     // P216 = sdk::NDIlib_FourCC_type_P216 as isize,
     // PA16 = sdk::NDIlib_FourCC_type_PA16 as isize,
+    /// 4:2:0 YUV, with a Y plane followed by V and then U planes at half the stride
     YV12 = sdk::NDIlib_FourCC_type_YV12 as isize,
+    /// 4:2:0 YUV, with a Y plane followed by U and then V planes at half the stride
     I420 = sdk::NDIlib_FourCC_type_I420 as isize,
+    /// 4:2:0 YUV, with a Y plane followed by a plane of interleaved U, V bytes
     NV12 = sdk::NDIlib_FourCC_type_NV12 as isize,
+    /// The bytes B, G, R, A for each pixel
     BGRA = sdk::NDIlib_FourCC_type_BGRA as isize,
+    /// The bytes B, G, R and an unused byte for each pixel, which should be treated as 255
     BGRX = sdk::NDIlib_FourCC_type_BGRX as isize,
+    /// The bytes R, G, B, A for each pixel
     RGBA = sdk::NDIlib_FourCC_type_RGBA as isize,
+    /// The bytes R, G, B and an unused byte for each pixel, which should be treated as 255
     RGBX = sdk::NDIlib_FourCC_type_RGBX as isize,
 }
 
+impl FourCCType {
+    /// Whether the format has an alpha channel
+    ///
+    /// NDI alpha is always straight, not premultiplied. Use `convert::premultiply_alpha` on RGBA data when
+    /// compositing with a renderer that expects premultiplied alpha.
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, FourCCType::UYVA | FourCCType::BGRA | FourCCType::RGBA)
+    }
+}
+
 impl TryFrom<u32> for FourCCType {
     type Error = ();
 
@@ -82,23 +151,77 @@ impl TryFrom<u32> for FourCCType {
     }
 }
 
+/// The format of a video stream, for detecting when a source changes resolution or frame rate mid stream
+///
+/// Anything sized from a previous frame, such as a texture, must be recreated when this changes.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct VideoFormat {
+    pub width: i32,
+    pub height: i32,
+    pub frame_rate_n: i32,
+    pub frame_rate_d: i32,
+    pub four_cc_type: FourCCType,
+    pub frame_format_type: FrameFormatType,
+}
+
+/// The properties of a video frame captured into a caller's buffer with `ReceiveInstanceExt::capture_video_into`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapturedVideoInfo {
+    pub format: VideoFormat,
+    /// The number of bytes between the start of each row in the buffer
+    pub line_stride: usize,
+    /// The number of bytes written to the buffer, which is 0 for a frame without data
+    pub data_len: usize,
+    /// The timecode of the frame, in 100ns units
+    pub timecode: i64,
+    /// The time the frame was sent, in 100ns units since the unix epoch, or `TIMESTAMP_UNDEFINED`
+    pub timestamp: i64,
+}
+
+/// The newest queued video frame, returned by `ReceiveInstanceExt::capture_latest_video`
+pub struct LatestVideoFrame {
+    pub frame: VideoFrame,
+    /// The number of older frames that were discarded to reach this one
+    pub dropped: usize,
+}
+
+/// Replace the last known format, logging when it changed
+pub(crate) fn update_video_format(last: &Mutex<Option<VideoFormat>>, format: VideoFormat) {
+    if let Ok(mut last) = last.lock() {
+        if *last != Some(format) {
+            if let Some(previous) = *last {
+                ndi_info!("Video format changed from {:?} to {:?}", previous, format);
+            }
+            *last = Some(format);
+        }
+    }
+}
+
 pub struct VideoFrame {
     id: usize,
     instance: Arc<Mutex<sdk::NDIlib_video_frame_v2_t>>,
-    parent: Weak<ReceiveInstance>,
+    parent: Weak<dyn FrameOwner>,
 
     pub width: i32,
     pub height: i32,
 
+    /// The numerator of the frame rate, eg 30000 for 29.97fps
     pub frame_rate_n: i32,
+    /// The denominator of the frame rate, eg 1001 for 29.97fps
     pub frame_rate_d: i32,
+    /// The pixel layout of the frame data. This depends on the `ReceiveColorFormat` and on whether the source has an
+    /// alpha channel, so it should be checked before using the data
     pub four_cc_type: FourCCType,
-    //    pub picture_aspect_ratio: f32,
+    /// The picture aspect ratio of the frame, eg 16/9. A value of 0 means the pixels are square
+    pub picture_aspect_ratio: f32,
     pub frame_format_type: FrameFormatType,
+    /// The timecode of the frame, in 100ns units. This is set by the sender, so is not guaranteed to be monotonic
     pub timecode: i64,
     //    pub p_data: *mut u8,
     //    pub line_stride_in_bytes: ::std::os::raw::c_int,
     //    pub p_metadata: *const ::std::os::raw::c_char,
+    /// The time the frame was sent, in 100ns units since the unix epoch, or `TIMESTAMP_UNDEFINED` if the sender
+    /// does not provide one
     pub timestamp: i64,
 }
 impl Drop for VideoFrame {
@@ -109,11 +232,137 @@ impl Drop for VideoFrame {
     }
 }
 impl VideoFrame {
-    pub fn lock_data(&self) -> Option<VideoFrameData> {
+    pub(crate) fn track(
+        store: &ReceiveDataStore<sdk::NDIlib_video_frame_v2_t>,
+        parent: Weak<dyn FrameOwner>,
+        video_data: sdk::NDIlib_video_frame_v2_t,
+    ) -> Result<VideoFrame, ReceiveCaptureError> {
+        match store.track(video_data) {
+            None => Err(ReceiveCaptureError::Poisoned),
+            Some(v) => Ok(VideoFrame {
+                id: v.0,
+                instance: v.1,
+                parent,
+
+                width: video_data.xres,
+                height: video_data.yres,
+
+                frame_rate_d: video_data.frame_rate_D,
+                frame_rate_n: video_data.frame_rate_N,
+                picture_aspect_ratio: video_data.picture_aspect_ratio,
+
+                timecode: video_data.timecode,
+                four_cc_type: FourCCType::try_from(video_data.FourCC)?,
+                frame_format_type: FrameFormatType::try_from(video_data.frame_format_type)?,
+                timestamp: video_data.timestamp,
+            }),
+        }
+    }
+    /// The format of the frame, to compare against the format of earlier frames
+    pub fn format(&self) -> VideoFormat {
+        VideoFormat {
+            width: self.width,
+            height: self.height,
+            frame_rate_n: self.frame_rate_n,
+            frame_rate_d: self.frame_rate_d,
+            four_cc_type: self.four_cc_type,
+            frame_format_type: self.frame_format_type,
+        }
+    }
+    /// Whether the resolution, frame rate or pixel layout differs from a previous format
+    ///
+    /// A source can switch modes at any time, eg a camera changing resolution, so anything sized from a previous
+    /// frame should be resized when this is true
+    pub fn format_changed_since(&self, previous: &VideoFormat) -> bool {
+        self.format() != *previous
+    }
+    /// Whether the frame is progressive, interlaced or a single field. Fields can be combined with
+    /// `deinterlace::Deinterlacer`
+    pub fn field(&self) -> FrameFormatType {
+        self.frame_format_type
+    }
+    /// The pixel layout of the frame data
+    pub fn four_cc(&self) -> FourCCType {
+        self.four_cc_type
+    }
+    /// Whether the frame has an alpha channel. The alpha is straight, not premultiplied
+    pub fn has_alpha(&self) -> bool {
+        self.four_cc_type.has_alpha()
+    }
+    /// The YUV matrix of the frame. RGBA data is converted from YUV by the SDK using this matrix, and is gamma
+    /// encoded rather than linear
+    pub fn color_matrix(&self) -> ColorMatrix {
+        ColorMatrix::for_height(self.height.max(0) as usize)
+    }
+    /// The display aspect ratio of the frame, falling back to the pixel dimensions when the source doesn't specify one
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.picture_aspect_ratio > 0.0 || self.height == 0 {
+            self.picture_aspect_ratio
+        } else {
+            self.width as f32 / self.height as f32
+        }
+    }
+    /// The frame rate in frames per second, or 0 if the frame rate is unknown
+    pub fn fps(&self) -> f64 {
+        if self.frame_rate_d == 0 {
+            0.0
+        } else {
+            self.frame_rate_n as f64 / self.frame_rate_d as f64
+        }
+    }
+    /// The XML metadata attached to the frame by the sender, if any
+    pub fn metadata(&self) -> Option<String> {
+        let locked = self.instance.lock().ok()?;
+        if locked.p_metadata.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(locked.p_metadata) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+    fn locked_data_len(frame: &sdk::NDIlib_video_frame_v2_t, four_cc: FourCCType) -> usize {
+        if frame.p_data.is_null() || frame.xres <= 0 || frame.yres <= 0 || frame.line_stride_in_bytes <= 0 {
+            0
+        } else {
+            frame_data_len(
+                four_cc,
+                frame.xres as usize,
+                frame.yres as usize,
+                frame.line_stride_in_bytes as usize,
+            )
+        }
+    }
+    /// The number of bytes of frame data, including any extra planes, or 0 if the frame has no data
+    pub fn data_len(&self) -> usize {
+        match self.instance.lock() {
+            Ok(locked) => Self::locked_data_len(&locked, self.four_cc_type),
+            Err(_) => 0,
+        }
+    }
+    /// Whether the frame has no pixel data
+    ///
+    /// A frame has no data once the receiver that captured it has been dropped, as that frees every frame it
+    /// returned, or if the SDK returned a frame with no size. This distinguishes that from the lock failing
+    pub fn is_empty(&self) -> bool {
+        self.data_len() == 0
+    }
+    /// Borrow the frame data without copying it
+    ///
+    /// The data is in the layout given by `four_cc()`, with rows `line_stride()` bytes apart. The frame can not be
+    /// freed while the returned guard is alive, so it can be copied directly into a GPU staging buffer or similar.
+    ///
+    /// Returns None when the frame has no data, see `is_empty()`, or if the frame's lock was poisoned by a panic.
+    pub fn lock_data(&self) -> Option<VideoFrameData<'_>> {
         if let Ok(locked) = self.instance.lock() {
+            let len = Self::locked_data_len(&locked, self.four_cc_type);
+            if len == 0 {
+                return None;
+            }
             unsafe {
-                let len = locked.line_stride_in_bytes * locked.yres;
-                let data = slice::from_raw_parts(locked.p_data, len as usize);
+                let data = slice::from_raw_parts(locked.p_data, len);
                 Some(GuardedPointer {
                     _guard: locked,
                     value: data,
@@ -123,23 +372,207 @@ impl VideoFrame {
             None
         }
     }
+
+    /// The number of bytes between the start of each row of the frame data
+    ///
+    /// This may be larger than the bytes needed for a row of pixels, so the data from `lock_data()` can not be assumed
+    /// to be tightly packed
+    pub fn line_stride(&self) -> usize {
+        match self.instance.lock() {
+            Ok(locked) => locked.line_stride_in_bytes as usize,
+            Err(_) => 0,
+        }
+    }
+    /// Convert the frame into tightly packed RGBA
+    ///
+    /// This can convert from all of the formats the SDK may provide, so allows for using the fastest color format
+    /// when receiving. Returns None if the data could not be converted
+    pub fn to_rgba(&self) -> Option<Vec<u8>> {
+        let stride = self.line_stride();
+        let data = self.lock_data()?;
+        convert::to_rgba(
+            &data,
+            self.four_cc_type,
+            self.width as usize,
+            self.height as usize,
+            stride,
+        )
+    }
+    /// Convert the frame into tightly packed RGBA, writing into an existing buffer of `width * height * 4` bytes
+    ///
+    /// Returns false if the data could not be converted
+    pub fn to_rgba_into(&self, out: &mut [u8]) -> bool {
+        let stride = self.line_stride();
+        match self.lock_data() {
+            Some(data) => convert::to_rgba_into(
+                &data,
+                self.four_cc_type,
+                self.width as usize,
+                self.height as usize,
+                stride,
+                out,
+            ),
+            None => false,
+        }
+    }
+    /// A fast hash of the frame data, for telling whether consecutive frames are new or repeats
+    ///
+    /// A frozen source may keep sending the same picture, so a run of frames with equal hashes suggests it has
+    /// stopped updating. The hash covers the whole buffer, including any row padding, and is 0 when the frame has no
+    /// data. It is not cryptographic, so shouldn't be used to compare against untrusted data.
+    pub fn data_hash(&self) -> u64 {
+        self.lock_data().map_or(0, |data| hash_bytes(&data))
+    }
+    /// Copy a rectangle out of the frame as RGBA, eg for one tile of a multiviewer
+    ///
+    /// The rectangle is clamped to the bounds of the frame, see `convert::crop_rgba`. The whole frame is converted
+    /// first, so cropping several tiles is cheaper with `to_rgba` and `convert::crop_rgba`. Returns None if the data
+    /// could not be converted
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Option<RgbaFrame> {
+        let rgba = self.to_rgba()?;
+        convert::crop_rgba(&rgba, self.width as usize, self.height as usize, x, y, width, height)
+    }
+    /// Resize the frame as RGBA, using bilinear filtering, eg for generating thumbnails
+    ///
+    /// Returns None if the data could not be converted, see `convert::scale_rgba`
+    pub fn scale(&self, width: usize, height: usize) -> Option<RgbaFrame> {
+        let rgba = self.to_rgba()?;
+        convert::scale_rgba(&rgba, self.width as usize, self.height as usize, width, height)
+    }
+    /// Copy the frame mirrored left to right, eg for showing a camera source to the person in front of it
+    ///
+    /// The copy is in the same format, tightly packed. Returns None for formats other than UYVY and RGB, see
+    /// `convert::flip_horizontal`
+    pub fn flip_horizontal(&self) -> Option<Vec<u8>> {
+        let data = self.lock_data()?;
+        convert::flip_horizontal(
+            &data,
+            self.four_cc_type,
+            self.width as usize,
+            self.height as usize,
+            self.line_stride(),
+        )
+    }
+    /// Copy the frame upside down
+    ///
+    /// The copy is in the same format, tightly packed. Returns None for formats other than UYVY and RGB, see
+    /// `convert::flip_vertical`
+    pub fn flip_vertical(&self) -> Option<Vec<u8>> {
+        let data = self.lock_data()?;
+        convert::flip_vertical(
+            &data,
+            self.four_cc_type,
+            self.width as usize,
+            self.height as usize,
+            self.line_stride(),
+        )
+    }
+    /// Convert the frame into I420 planes, for feeding a video encoder
+    ///
+    /// Returns None for RGB formats, see `convert::to_i420`
+    pub fn to_i420(&self) -> Option<I420Planes> {
+        let data = self.lock_data()?;
+        convert::to_i420(
+            &data,
+            self.four_cc_type,
+            self.width as usize,
+            self.height as usize,
+            self.line_stride(),
+        )
+    }
+    /// Convert the frame into an image buffer, eg for saving a snapshot with `frame.to_image_buffer()?.save(path)`
+    ///
+    /// Returns None if the data could not be converted
+    #[cfg(feature = "image")]
+    pub fn to_image_buffer(&self) -> Option<image::RgbaImage> {
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, self.to_rgba()?)
+    }
+    /// The texture format matching the frame data, or None for YUV formats which need converting first
+    ///
+    /// NDI RGB data is gamma encoded, so the sRGB formats are used
+    #[cfg(feature = "wgpu")]
+    pub fn texture_format(&self) -> Option<wgpu::TextureFormat> {
+        match self.four_cc_type {
+            FourCCType::RGBA | FourCCType::RGBX => Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+            FourCCType::BGRA | FourCCType::BGRX => Some(wgpu::TextureFormat::Bgra8UnormSrgb),
+            _ => None,
+        }
+    }
+    /// Upload the frame into a texture, using the line stride of the frame so no intermediate copy is needed
+    ///
+    /// The texture must be the same size as the frame, and have the format given by `texture_format()`, with or
+    /// without the sRGB suffix. For YUV frames, or when the size has changed, this returns false and the texture
+    /// should be recreated or the frame converted with `to_rgba()` instead.
+    #[cfg(feature = "wgpu")]
+    pub fn write_to_texture(&self, queue: &wgpu::Queue, texture: &wgpu::Texture) -> bool {
+        let format = match self.texture_format() {
+            Some(format) => format,
+            None => return false,
+        };
+        if texture.format().remove_srgb_suffix() != format.remove_srgb_suffix()
+            || texture.width() != self.width as u32
+            || texture.height() != self.height as u32
+        {
+            return false;
+        }
+
+        let stride = self.line_stride();
+        let data = match self.lock_data() {
+            Some(data) => data,
+            None => return false,
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(stride as u32),
+                rows_per_image: Some(self.height as u32),
+            },
+            wgpu::Extent3d {
+                width: self.width as u32,
+                height: self.height as u32,
+                depth_or_array_layers: 1,
+            },
+        );
+        true
+    }
+    /// Copy the frame into tightly packed RGBA, with any padding at the end of rows removed
+    ///
+    /// This is the same as `to_rgba()`, but returns an empty Vec if the data could not be converted
+    pub fn copy_packed_rgba(&self) -> Vec<u8> {
+        self.to_rgba().unwrap_or_default()
+    }
 }
 
+/// The samples of an audio frame, borrowed from it by `AudioFrame::lock_data`. This protects the frame in the same
+/// way as `VideoFrameData`
 pub type AudioFrameData<'a> = GuardedPointer<'a, sdk::NDIlib_audio_frame_v2_t, f32>;
 unsafe impl Send for AudioFrame {}
 unsafe impl Sync for AudioFrame {}
+/// A received frame of planar 32bit floating point audio
+///
+/// The frame is returned to the SDK when this is dropped
 pub struct AudioFrame {
     id: usize,
     instance: Arc<Mutex<sdk::NDIlib_audio_frame_v2_t>>,
-    parent: Weak<ReceiveInstance>,
+    parent: Weak<dyn FrameOwner>,
 
     pub sample_rate: i32,
     pub channel_count: i32,
+    /// The number of samples in each channel
     pub sample_count: i32,
+    /// The timecode of the frame, in 100ns units
     pub timecode: i64,
     //    pub p_data: *mut f32,
     //    pub channel_stride_in_bytes: ::std::os::raw::c_int,
     //    pub p_metadata: *const ::std::os::raw::c_char,
+    /// The time the frame was sent, in 100ns units since the unix epoch, or `TIMESTAMP_UNDEFINED`
     pub timestamp: i64,
 }
 impl Drop for AudioFrame {
@@ -150,11 +583,50 @@ impl Drop for AudioFrame {
     }
 }
 impl AudioFrame {
-    pub fn lock_data(&self) -> Option<AudioFrameData> {
+    pub(crate) fn track(
+        store: &ReceiveDataStore<sdk::NDIlib_audio_frame_v2_t>,
+        parent: Weak<dyn FrameOwner>,
+        audio_data: sdk::NDIlib_audio_frame_v2_t,
+    ) -> Result<AudioFrame, ReceiveCaptureError> {
+        match store.track(audio_data) {
+            None => Err(ReceiveCaptureError::Poisoned),
+            Some(v) => Ok(AudioFrame {
+                id: v.0,
+                instance: v.1,
+                parent,
+
+                sample_rate: audio_data.sample_rate,
+                channel_count: audio_data.no_channels,
+                sample_count: audio_data.no_samples,
+                timecode: audio_data.timecode,
+                timestamp: audio_data.timestamp,
+            }),
+        }
+    }
+    /// The XML metadata attached to the frame by the sender, if any
+    pub fn metadata(&self) -> Option<String> {
+        let locked = self.instance.lock().ok()?;
+        if locked.p_metadata.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(locked.p_metadata) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+    /// Lock the audio samples for reading
+    ///
+    /// The samples are planar, with each channel stored one after another
+    pub fn lock_data(&self) -> Option<AudioFrameData<'_>> {
         if let Ok(locked) = self.instance.lock() {
             unsafe {
+                if locked.p_data.is_null() {
+                    return None;
+                }
                 // Divide by four as this is a list of f32
-                let len = locked.channel_stride_in_bytes * locked.no_channels / 4; 
+                let len = locked.channel_stride_in_bytes * locked.no_channels / 4;
                 let data = slice::from_raw_parts(locked.p_data, len as usize);
                 Some(GuardedPointer {
                     _guard: locked,
@@ -165,20 +637,175 @@ impl AudioFrame {
             None
         }
     }
+
+    /// The position of the start of this frame relative to a reference timecode, in samples at the frame's rate
+    ///
+    /// This lines audio up against video when recording. Pass the timecode of a video frame to get how many samples
+    /// after it the audio starts, which is negative if the audio starts first. The result is rounded to the nearest
+    /// sample.
+    pub fn sample_offset_from(&self, reference_timecode: i64) -> i64 {
+        // Timecodes are in 100ns units, so there are 10,000,000 per second
+        let ticks = self.timecode as i128 - reference_timecode as i128;
+        let scaled = ticks * self.sample_rate as i128;
+        let offset = if scaled >= 0 {
+            (scaled + 5_000_000) / 10_000_000
+        } else {
+            (scaled - 5_000_000) / 10_000_000
+        };
+        offset as i64
+    }
+
+    /// Copy the audio into an owned buffer, which can be processed further
+    pub fn to_buffer(&self) -> Option<AudioBuffer> {
+        let locked = self.instance.lock().ok()?;
+        let channel_count = locked.no_channels.max(0) as usize;
+        let sample_count = locked.no_samples.max(0) as usize;
+
+        let mut data = Vec::with_capacity(channel_count * sample_count);
+        if !locked.p_data.is_null() {
+            // Divide by four as this is a list of f32
+            let channel_stride = locked.channel_stride_in_bytes as usize / 4;
+            let planar = unsafe { slice::from_raw_parts(locked.p_data, channel_stride * channel_count) };
+            for channel in 0..channel_count {
+                data.extend_from_slice(&planar[channel * channel_stride..channel * channel_stride + sample_count]);
+            }
+        }
+
+        Some(AudioBuffer {
+            sample_rate: locked.sample_rate,
+            channel_count,
+            sample_count: if data.is_empty() { 0 } else { sample_count },
+            timecode: locked.timecode,
+            data,
+        })
+    }
+
+    /// Copy the audio into an owned buffer, resampled to a different sample rate
+    ///
+    /// See `AudioBuffer::resample` for details
+    pub fn resample(&self, target_rate: i32) -> Option<AudioBuffer> {
+        Some(self.to_buffer()?.resample(target_rate))
+    }
+
+    /// Copy the audio into an owned buffer, with a different number of channels
+    ///
+    /// See `AudioBuffer::remap_channels` for the mapping used
+    pub fn remap_channels(&self, target_channels: usize) -> Option<AudioBuffer> {
+        Some(self.to_buffer()?.remap_channels(target_channels))
+    }
+
+    /// Copy the audio samples into a new buffer, interleaving the channels
+    pub fn to_interleaved(&self) -> Option<Vec<f32>> {
+        if let Ok(locked) = self.instance.lock() {
+            if locked.p_data.is_null() || locked.no_channels <= 0 || locked.no_samples <= 0 {
+                return Some(vec![]);
+            }
+
+            // Divide by four as this is a list of f32
+            let channel_stride = locked.channel_stride_in_bytes as usize / 4;
+            let len = channel_stride * locked.no_channels as usize;
+            let data = unsafe { slice::from_raw_parts(locked.p_data, len) };
+            Some(interleave_audio(
+                data,
+                locked.no_channels as usize,
+                locked.no_samples as usize,
+                channel_stride,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// A metadata frame, containing an XML string
+#[derive(Debug, Clone)]
+pub struct MetadataFrame {
+    pub xml: String,
+    pub timecode: i64,
+}
+impl MetadataFrame {
+    /// Create a metadata frame, with the timecode synthesized by the SDK when it is sent
+    pub fn new(xml: String) -> MetadataFrame {
+        MetadataFrame {
+            xml,
+            timecode: TIMECODE_SYNTHESIZE,
+        }
+    }
 }
 
-struct ReceiveDataStore<T> {
-    data: Mutex<HashMap<usize, Arc<Mutex<T>>>>,
+/// The product details a source advertises, eg for keeping an inventory of the devices on a network
+///
+/// Sources send these in an `ndi_product` metadata message when a receiver connects. Sources which don't say, such
+/// as most software senders, leave the fields as None.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct SourceInfo {
+    pub product_name: Option<String>,
+    pub model_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub serial: Option<String>,
+    pub version: Option<String>,
+}
+impl SourceInfo {
+    /// Parse the XML of an `ndi_product` message, or None if the XML is not one
+    pub fn parse(xml: &str) -> Option<SourceInfo> {
+        let element = xml_element(xml, "ndi_product")?;
+        let attribute = |name| xml_attribute(element, name).filter(|v| !v.is_empty());
+        Some(SourceInfo {
+            product_name: attribute("long_name").or_else(|| attribute("short_name")),
+            model_name: attribute("model_name"),
+            manufacturer: attribute("manufacturer"),
+            serial: attribute("serial"),
+            version: attribute("version"),
+        })
+    }
+}
+
+/// The tally state of a source
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct Tally {
+    pub on_program: bool,
+    pub on_preview: bool,
+}
+
+/// Frame counts of a receiver since it was created
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct ReceivePerformance {
+    pub video_total: i64,
+    pub video_dropped: i64,
+    pub audio_total: i64,
+    pub audio_dropped: i64,
+    pub metadata_total: i64,
+    pub metadata_dropped: i64,
+}
+
+/// The number of frames waiting to be captured from a receiver
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct ReceiveQueue {
+    pub video_frames: i32,
+    pub audio_frames: i32,
+    pub metadata_frames: i32,
+}
+
+/// Something which owns captured frames, and must be told when they are dropped
+pub(crate) trait FrameOwner {
+    fn free_video(&self, id: usize);
+    fn free_audio(&self, id: usize);
+}
+
+pub(crate) struct ReceiveDataStore<T> {
+    pub(crate) data: Mutex<HashMap<usize, Arc<Mutex<T>>>>,
     next_id: AtomicUsize,
 }
 impl<T> ReceiveDataStore<T> {
-    fn remove(&self, id: usize) -> Option<Arc<Mutex<T>>> {
+    pub(crate) fn new() -> ReceiveDataStore<T> {
+        ReceiveDataStore {
+            data: Mutex::new(HashMap::new()),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+    pub(crate) fn remove(&self, id: usize) -> Option<Arc<Mutex<T>>> {
         if let Ok(mut data_store) = self.data.lock() {
-            if let Some(data) = data_store.remove(&id) {
-                Some(data)
-            } else {
-                None
-            }
+            data_store.remove(&id)
         } else {
             None
         }
@@ -197,12 +824,32 @@ impl<T> ReceiveDataStore<T> {
 }
 
 unsafe impl Send for ReceiveInstance {}
-unsafe impl Sync for ReceiveInstance {} // TODO - is this true? what is safety of methods on instance like?
+unsafe impl Sync for ReceiveInstance {}
+/// A receiver, which can connect to a source
+///
+/// The SDK documents a receiver as safe to use from multiple threads, including capturing video, audio and metadata
+/// from separate threads at the same time, so this can be shared between threads or tasks in an `Arc`. Frames are
+/// tracked behind mutexes, so they can be dropped from any thread.
+///
+/// Frames are always delivered decoded. This version of the SDK does not report how a source is compressed, so NDI
+/// HX sources, which are H.264 or H.265 and cost more CPU to decode, can't be told apart from full bandwidth ones.
 pub struct ReceiveInstance {
-    sdk_instance: Arc<NDIHandle>,
-    instance: sdk::NDIlib_recv_instance_t,
+    pub(crate) sdk_instance: Arc<NDIHandle>,
+    pub(crate) instance: sdk::NDIlib_recv_instance_t,
     video_frames: ReceiveDataStore<sdk::NDIlib_video_frame_v2_t>,
     audio_frames: ReceiveDataStore<sdk::NDIlib_audio_frame_v2_t>,
+    tally: Mutex<Tally>,
+    source: Mutex<Option<FindSource>>,
+    video_format: Mutex<Option<VideoFormat>>,
+    source_info: Mutex<Option<SourceInfo>>,
+    bandwidth: ReceiveBandwidth,
+    color_format: ReceiveColorFormat,
+    name: Option<String>,
+    allow_video_fields: bool,
+    capture_timeout: u32,
+    max_retries: u32,
+    reconnect_backoff: Option<ReconnectBackoff>,
+    reconnect: Mutex<Option<ReconnectHandle>>,
 }
 impl Drop for ReceiveInstance {
     fn drop(&mut self) {
@@ -223,29 +870,299 @@ impl Drop for ReceiveInstance {
     }
 }
 impl ReceiveInstance {
-    pub fn connect(&self, source: Option<&FindSource>) -> bool {
+    /// The SDK handle of the receiver. See the `raw` module for the safety rules
+    #[cfg(feature = "unsafe-raw")]
+    pub fn raw_instance(&self) -> sdk::NDIlib_recv_instance_t {
+        self.instance
+    }
+    /// Connect to a source, or disconnect when the source is None
+    pub fn connect(&self, source: Option<&FindSource>) -> Result<(), NdiError> {
         match source {
-            None => unsafe {
-                self.sdk_instance.NDIlib_recv_connect.unwrap()(self.instance, null());
-                true
-            },
+            None => {
+                unsafe {
+                    self.sdk_instance.NDIlib_recv_connect.unwrap()(self.instance, null());
+                }
+                ndi_info!("Receiver disconnected");
+            }
             Some(s) => {
-                if let Ok(s2) = to_ndi_source(s) {
-                    unsafe {
-                        self.sdk_instance.NDIlib_recv_connect.unwrap()(self.instance, &s2.2);
-                    }
-
-                    true
-                } else {
-                    false
+                let s2 = to_ndi_source(s).map_err(|_| NdiError::InvalidSource)?;
+                unsafe {
+                    self.sdk_instance.NDIlib_recv_connect.unwrap()(self.instance, &s2.2);
                 }
+                ndi_info!("Receiver connecting to {}", s.name);
             }
         }
+
+        if let Ok(mut current) = self.source.lock() {
+            *current = source.cloned();
+        }
+        if let Ok(mut info) = self.source_info.lock() {
+            *info = None;
+        }
+        Ok(())
     }
-    fn free_video(&self, id: usize) {
-        if let Some(frame) = self.video_frames.remove(id) {
-            self.free_video_inner(&frame);
+    /// The source the receiver is connected to, or None when it is not connected
+    ///
+    /// This version of the SDK can't be asked for the source, so this is the source last passed to `connect`, either
+    /// directly or through `connect_by_name`, `with_source` or auto reconnect
+    pub fn current_source(&self) -> Option<FindSource> {
+        if !self.is_connected() {
+            return None;
+        }
+        self.source.lock().ok().and_then(|s| s.clone())
+    }
+    /// The source last passed to `connect`, whether or not it is connected
+    pub(crate) fn target_source(&self) -> Option<FindSource> {
+        self.source.lock().ok().and_then(|s| s.clone())
+    }
+    /// Find a source by name and connect to it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to look for, matched as described by `FindSource::matches_name`
+    /// * `timeout` - How long to wait for the source to be found, in milliseconds
+    ///
+    /// # Returns
+    ///
+    /// The source that was connected to, or `NdiError::SourceNotFound` if it was not found before the timeout
+    ///
+    pub fn connect_by_name(&self, name: &str, timeout: u32) -> Result<FindSource, NdiError> {
+        let finder = create_find_instance(self.sdk_instance.clone(), true).ok_or(NdiError::CreateFailed)?;
+        let source = finder
+            .wait_for_source(timeout, |s| s.matches_name(name))
+            .ok_or(NdiError::SourceNotFound)?;
+
+        self.connect(Some(&source))?;
+        Ok(source)
+    }
+    /// The name the receiver was created with, if one was set
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    /// The color format the receiver was created with. The format of each frame is given by `VideoFrame::four_cc()`
+    pub fn color_format(&self) -> ReceiveColorFormat {
+        self.color_format
+    }
+    /// The bandwidth the receiver was created with
+    pub fn bandwidth(&self) -> ReceiveBandwidth {
+        self.bandwidth
+    }
+    /// Create a new receiver with the same settings but a different bandwidth, connected to the same source
+    ///
+    /// The SDK can't change the bandwidth of an existing receiver, so this is the way to switch between a full
+    /// quality program feed and a low bandwidth preview. The new receiver connects in parallel, so keep using this one
+    /// until the new one has a frame to avoid a gap. Auto reconnect is not carried over.
+    pub fn recreate_with_bandwidth(
+        &self,
+        bandwidth: ReceiveBandwidth,
+    ) -> Result<Arc<ReceiveInstance>, ReceiveCreateError> {
+        let mut builder = create_receive_builder(self.sdk_instance.clone())
+            .with_bandwidth(bandwidth)
+            .with_color_format(self.color_format)
+            .with_allow_video_fields(self.allow_video_fields)
+            .with_capture_timeout(self.capture_timeout)
+            .with_max_retries(self.max_retries);
+        if let Some(backoff) = self.reconnect_backoff {
+            builder = builder.with_reconnect_backoff(backoff);
+        }
+        if let Some(name) = &self.name {
+            builder = builder.with_name(name.clone());
+        }
+        if let Some(source) = self.source.lock().ok().and_then(|s| s.clone()) {
+            builder = builder.with_source(source);
+        }
+        builder.build()
+    }
+    /// The product details advertised by the connected source, or None if it hasn't sent any
+    ///
+    /// These are read from the metadata frames the receiver captures, so metadata has to be captured for this to be
+    /// set. The details are cleared when connecting to a different source
+    pub fn source_info(&self) -> Option<SourceInfo> {
+        self.source_info.lock().ok().and_then(|i| i.clone())
+    }
+    /// The format of the last video frame captured from the receiver, or None if no video has been captured yet
+    pub fn last_video_format(&self) -> Option<VideoFormat> {
+        self.video_format.lock().ok().and_then(|f| *f)
+    }
+    /// Stop automatically reconnecting, if it was enabled with `enable_auto_reconnect` or `with_reconnect_backoff`
+    pub fn disable_auto_reconnect(&self) {
+        if let Ok(mut reconnect) = self.reconnect.lock() {
+            *reconnect = None;
+        }
+    }
+    /// Send a metadata frame to the connected source
+    ///
+    /// This is also the channel used for control messages, such as KVM input for sources which support it. There is
+    /// no dedicated KVM API in this version of the SDK, so the XML for those messages must be built by the caller.
+    pub fn send_metadata(&self, frame: &MetadataFrame) -> Result<(), NdiError> {
+        let metadata = to_ndi_metadata(frame).map_err(|_| NdiError::InvalidMetadata)?;
+        if unsafe { self.sdk_instance.NDIlib_recv_send_metadata.unwrap()(self.instance, &metadata.1) } {
+            Ok(())
+        } else {
+            Err(NdiError::NotConnected)
+        }
+    }
+    /// Add metadata which is sent to the source every time the receiver connects, including after reconnecting
+    ///
+    /// This lets a source configure itself for the receiver, eg a source which supports it can be asked for a
+    /// particular resolution. This only affects future connections.
+    pub fn add_connection_metadata(&self, frame: &MetadataFrame) -> Result<(), NdiError> {
+        let metadata = to_ndi_metadata(frame).map_err(|_| NdiError::InvalidMetadata)?;
+        unsafe {
+            self.sdk_instance.NDIlib_recv_add_connection_metadata.unwrap()(self.instance, &metadata.1);
+        }
+        Ok(())
+    }
+    /// Remove all metadata added with `add_connection_metadata`
+    pub fn clear_connection_metadata(&self) {
+        unsafe {
+            self.sdk_instance.NDIlib_recv_clear_connection_metadata.unwrap()(self.instance);
+        }
+    }
+    /// Tell the connected source whether it is on program and/or preview
+    ///
+    /// Returns false if the tally could not be sent
+    pub fn set_tally(&self, on_program: bool, on_preview: bool) -> bool {
+        let tally = sdk::NDIlib_tally_t { on_program, on_preview };
+        let res = unsafe { self.sdk_instance.NDIlib_recv_set_tally.unwrap()(self.instance, &tally) };
+        if res {
+            if let Ok(mut current) = self.tally.lock() {
+                *current = Tally { on_program, on_preview };
+            }
         }
+        res
+    }
+    /// Get the tally state last successfully sent with `set_tally`
+    pub fn get_tally(&self) -> Tally {
+        self.tally.lock().map(|t| *t).unwrap_or_default()
+    }
+    /// The number of connections the receiver currently has to its source
+    ///
+    /// Unlike the sender equivalent, the SDK does not support waiting for a connection, so this returns immediately
+    pub fn get_num_connections(&self) -> i32 {
+        unsafe { self.sdk_instance.NDIlib_recv_get_no_connections.unwrap()(self.instance) }
+    }
+    /// Whether the receiver is currently connected to its source
+    pub fn is_connected(&self) -> bool {
+        self.get_num_connections() > 0
+    }
+    /// Get the total and dropped frame counts of the receiver
+    ///
+    /// Frames are dropped when they are not captured quickly enough, so a rising dropped count means the application
+    /// is the bottleneck rather than the network
+    pub fn get_performance(&self) -> ReceivePerformance {
+        let mut total = sdk::NDIlib_recv_performance_t {
+            video_frames: 0,
+            audio_frames: 0,
+            metadata_frames: 0,
+        };
+        let mut dropped = total;
+        unsafe { self.sdk_instance.NDIlib_recv_get_performance.unwrap()(self.instance, &mut total, &mut dropped) };
+
+        ReceivePerformance {
+            video_total: total.video_frames,
+            video_dropped: dropped.video_frames,
+            audio_total: total.audio_frames,
+            audio_dropped: dropped.audio_frames,
+            metadata_total: total.metadata_frames,
+            metadata_dropped: dropped.metadata_frames,
+        }
+    }
+    /// Get the number of frames currently queued waiting to be captured
+    pub fn get_queue(&self) -> ReceiveQueue {
+        let mut queue = sdk::NDIlib_recv_queue_t {
+            video_frames: 0,
+            audio_frames: 0,
+            metadata_frames: 0,
+        };
+        unsafe { self.sdk_instance.NDIlib_recv_get_queue.unwrap()(self.instance, &mut queue) };
+
+        ReceiveQueue {
+            video_frames: queue.video_frames,
+            audio_frames: queue.audio_frames,
+            metadata_frames: queue.metadata_frames,
+        }
+    }
+    /// Get the URL of the web control interface of the connected source, or None if it does not offer one
+    pub fn get_web_control(&self) -> Option<String> {
+        unsafe {
+            let url = self.sdk_instance.NDIlib_recv_get_web_control.unwrap()(self.instance);
+            if url.is_null() {
+                None
+            } else {
+                let res = CStr::from_ptr(url).to_string_lossy().into_owned();
+                self.sdk_instance.NDIlib_recv_free_string.unwrap()(self.instance, url);
+                Some(res)
+            }
+        }
+    }
+    /// Whether the connected source supports PTZ control
+    pub fn is_ptz_supported(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_is_supported.unwrap()(self.instance) }
+    }
+    /// Set the zoom level, from 0.0 (zoomed in) to 1.0 (zoomed out)
+    pub fn ptz_zoom(&self, zoom_value: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_zoom.unwrap()(self.instance, zoom_value) }
+    }
+    /// Set the zoom speed, from -1.0 (zoom outwards) to 1.0 (zoom inwards)
+    pub fn ptz_zoom_speed(&self, zoom_speed: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_zoom_speed.unwrap()(self.instance, zoom_speed) }
+    }
+    /// Set the pan and tilt position, each from -1.0 to 1.0
+    pub fn ptz_pan_tilt(&self, pan_value: f32, tilt_value: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_pan_tilt.unwrap()(self.instance, pan_value, tilt_value) }
+    }
+    /// Set the pan and tilt speed, each from -1.0 to 1.0
+    pub fn ptz_pan_tilt_speed(&self, pan_speed: f32, tilt_speed: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_pan_tilt_speed.unwrap()(self.instance, pan_speed, tilt_speed) }
+    }
+    /// Store the current position as a preset, from 0 to 99
+    pub fn ptz_store_preset(&self, preset_no: i32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_store_preset.unwrap()(self.instance, preset_no) }
+    }
+    /// Recall a preset, from 0 to 99, moving at a speed from 0.0 (slowest) to 1.0 (fastest)
+    pub fn ptz_recall_preset(&self, preset_no: i32, speed: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_recall_preset.unwrap()(self.instance, preset_no, speed) }
+    }
+    /// Enable auto focus
+    pub fn ptz_auto_focus(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_auto_focus.unwrap()(self.instance) }
+    }
+    /// Set the focus, from 0.0 (infinity) to 1.0 (as close as possible)
+    pub fn ptz_focus(&self, focus_value: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_focus.unwrap()(self.instance, focus_value) }
+    }
+    /// Set the focus speed, from -1.0 (focus outwards) to 1.0 (focus inwards)
+    pub fn ptz_focus_speed(&self, focus_speed: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_focus_speed.unwrap()(self.instance, focus_speed) }
+    }
+    /// Enable automatic white balance
+    pub fn ptz_white_balance_auto(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_white_balance_auto.unwrap()(self.instance) }
+    }
+    /// Use the indoor white balance preset
+    pub fn ptz_white_balance_indoor(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_white_balance_indoor.unwrap()(self.instance) }
+    }
+    /// Use the outdoor white balance preset
+    pub fn ptz_white_balance_outdoor(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_white_balance_outdoor.unwrap()(self.instance) }
+    }
+    /// Set the white balance from the current scene, and hold it
+    pub fn ptz_white_balance_oneshot(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_white_balance_oneshot.unwrap()(self.instance) }
+    }
+    /// Set the white balance manually, with red and blue each from 0.0 to 1.0
+    pub fn ptz_white_balance_manual(&self, red: f32, blue: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_white_balance_manual.unwrap()(self.instance, red, blue) }
+    }
+    /// Enable automatic exposure
+    pub fn ptz_exposure_auto(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_exposure_auto.unwrap()(self.instance) }
+    }
+    /// Set the exposure manually, from 0.0 (dark) to 1.0 (light)
+    pub fn ptz_exposure_manual(&self, exposure_level: f32) -> bool {
+        unsafe { self.sdk_instance.NDIlib_recv_ptz_exposure_manual.unwrap()(self.instance, exposure_level) }
     }
     fn free_video_inner(&self, video: &Arc<Mutex<sdk::NDIlib_video_frame_v2_t>>) {
         if let Ok(mut ndi_ref) = video.lock() {
@@ -257,11 +1174,6 @@ impl ReceiveInstance {
             // TODO - ?
         }
     }
-    fn free_audio(&self, id: usize) {
-        if let Some(frame) = self.audio_frames.remove(id) {
-            self.free_audio_inner(&frame);
-        }
-    }
     fn free_audio_inner(&self, audio: &Arc<Mutex<sdk::NDIlib_audio_frame_v2_t>>) {
         if let Ok(mut ndi_ref) = audio.lock() {
             unsafe {
@@ -274,6 +1186,19 @@ impl ReceiveInstance {
     }
 }
 
+impl FrameOwner for ReceiveInstance {
+    fn free_video(&self, id: usize) {
+        if let Some(frame) = self.video_frames.remove(id) {
+            self.free_video_inner(&frame);
+        }
+    }
+    fn free_audio(&self, id: usize) {
+        if let Some(frame) = self.audio_frames.remove(id) {
+            self.free_audio_inner(&frame);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ReceiveCaptureError {
     Failed, // TODO
@@ -287,7 +1212,8 @@ impl From<()> for ReceiveCaptureError {
     }
 }
 
-#[derive(Debug)]
+/// The type of frame returned by a capture
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum ReceiveCaptureResultType {
     None,
     Video,
@@ -296,11 +1222,35 @@ pub enum ReceiveCaptureResultType {
     Error,
     StatusChange,
 }
+/// A captured frame
+///
+/// Any video or audio frame is held by the SDK until it is dropped
 pub enum ReceiveCaptureResult {
     None,
     Video(VideoFrame),
     Audio(AudioFrame),
-    Metadata(u32),
+    Metadata(MetadataFrame),
+    /// The properties of the source changed, such as its web control URL, PTZ support or connection count, and they
+    /// should be queried again
+    StatusChange,
+    /// The SDK reported an error, usually because the connection to the source was lost
+    ///
+    /// This is recoverable, as the SDK keeps trying to reconnect, so capturing can simply be retried. Hard failures,
+    /// where the receiver can't be used, are returned as `Err` instead.
+    Error,
+}
+impl ReceiveCaptureResult {
+    /// The type of the captured frame, without needing to match on the data
+    pub fn frame_type(&self) -> ReceiveCaptureResultType {
+        match self {
+            ReceiveCaptureResult::None => ReceiveCaptureResultType::None,
+            ReceiveCaptureResult::Video(_) => ReceiveCaptureResultType::Video,
+            ReceiveCaptureResult::Audio(_) => ReceiveCaptureResultType::Audio,
+            ReceiveCaptureResult::Metadata(_) => ReceiveCaptureResultType::Metadata,
+            ReceiveCaptureResult::StatusChange => ReceiveCaptureResultType::StatusChange,
+            ReceiveCaptureResult::Error => ReceiveCaptureResultType::Error,
+        }
+    }
 }
 
 pub trait ReceiveInstanceExt {
@@ -310,7 +1260,150 @@ pub trait ReceiveInstanceExt {
         capture_audio: bool,
         capture_metadata: bool,
         timeout: u32,
-    ) -> Result<ReceiveCaptureResult, ReceiveCaptureError>;
+    ) -> Result<ReceiveCaptureResult, NdiError>;
+
+    /// Capture a frame using the timeout and retry count set on the builder
+    ///
+    /// Attempts which time out or return `ReceiveCaptureResult::Error` are retried up to `with_max_retries` times,
+    /// after which the last result is returned. Anything else is returned straight away.
+    fn capture(
+        &self,
+        capture_video: bool,
+        capture_audio: bool,
+        capture_metadata: bool,
+    ) -> Result<ReceiveCaptureResult, NdiError>;
+
+    /// Wait for a video frame
+    ///
+    /// # Returns
+    ///
+    /// The frame, None if no frame arrived before the timeout, or `NdiError::ConnectionLost` if the receiver reported
+    /// an error, eg because the connection was lost
+    ///
+    fn capture_video(&self, timeout: u32) -> Result<Option<VideoFrame>, NdiError>;
+
+    /// Wait for an audio frame
+    ///
+    /// # Returns
+    ///
+    /// The frame, None if no frame arrived before the timeout, or `NdiError::ConnectionLost` if the receiver reported
+    /// an error, eg because the connection was lost
+    ///
+    fn capture_audio(&self, timeout: u32) -> Result<Option<AudioFrame>, NdiError>;
+
+    /// Wait for a metadata frame
+    ///
+    /// # Returns
+    ///
+    /// The frame, None if no frame arrived before the timeout, or `NdiError::ConnectionLost` if the receiver reported
+    /// an error, eg because the connection was lost
+    ///
+    fn capture_metadata(&self, timeout: u32) -> Result<Option<MetadataFrame>, NdiError>;
+
+    /// Wait for a frame of any type
+    fn capture_any(&self, timeout: u32) -> Result<ReceiveCaptureResult, NdiError>;
+
+    /// Wait for a video frame and copy its data into a buffer owned by the caller
+    ///
+    /// The frame is handed straight back to the SDK once it has been copied, so no frames are held and nothing is
+    /// allocated. This allows buffers to be managed by the application, eg rotating through a ring of preallocated
+    /// buffers shared with a render thread. The data is copied as is, in the layout described by the returned info.
+    ///
+    /// # Returns
+    ///
+    /// The properties of the frame, None if no frame arrived before the timeout, or `NdiError::BufferTooSmall` with
+    /// the required size if the frame didn't fit, in which case the frame is lost. A frame without data, see
+    /// `VideoFrame::is_empty`, is returned with a `data_len` of 0 and nothing written. Capture errors are returned as
+    /// by `capture_video`
+    ///
+    fn capture_video_into(&self, timeout: u32, buffer: &mut [u8]) -> Result<Option<CapturedVideoInfo>, NdiError>;
+
+    /// Capture the most recent queued video frame, discarding any older frames, without blocking
+    ///
+    /// This is intended for displaying a source, where only the newest frame matters. If the display falls behind,
+    /// frames queue up in the SDK, and showing each of them in turn would add latency.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_drain` - The maximum number of frames to discard, so a source sending faster than the frames can be
+    ///   captured can't keep this looping forever
+    ///
+    /// # Returns
+    ///
+    /// The newest frame and how many were dropped, or None if no frames were queued. A high drop count suggests the
+    /// display can't keep up with the source. Capture errors are returned as by `capture_video`
+    ///
+    fn capture_latest_video(&self, max_drain: usize) -> Result<Option<LatestVideoFrame>, NdiError>;
+
+    /// Capture a video frame if one is ready, without blocking
+    ///
+    /// Errors are discarded, so use `capture_video` with a timeout of 0 to tell a lost connection from no frame
+    fn try_receive_video(&self) -> Option<VideoFrame>;
+
+    /// Capture an audio frame if one is ready, without blocking
+    fn try_receive_audio(&self) -> Option<AudioFrame>;
+
+    /// Capture a metadata frame if one is ready, without blocking
+    fn try_receive_metadata(&self) -> Option<MetadataFrame>;
+
+    /// Wait for the properties of the source to change, without capturing any frames
+    ///
+    /// # Returns
+    ///
+    /// `ReceiveCaptureResultType::StatusChange` if the source changed, or `ReceiveCaptureResultType::None` if the
+    /// timeout expired first. This distinguishes a timeout from a status change without allocating any frames
+    ///
+    fn wait_for_status_change(&self, timeout: u32) -> Result<ReceiveCaptureResultType, NdiError>;
+
+    /// Iterate over captured frames of all types, until capturing fails
+    ///
+    /// A `ReceiveCaptureResult::None` is produced each time the timeout expires without a frame
+    fn frames(&self, timeout: u32) -> ReceiveFrames;
+
+    /// Iterate over captured video frames, until capturing fails or the connection is lost
+    fn video_frames(&self, timeout: u32) -> ReceiveVideoFrames;
+
+    /// Attach a frame synchronizer to this receiver
+    ///
+    /// While the frame synchronizer exists, this receiver should not be used to capture frames
+    fn create_framesync(&self) -> Result<Arc<FrameSyncInstance>, FrameSyncCreateError>;
+
+    /// Capture frames of all types and write them to a sink, until the sink reports it is finished
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink to write frames to
+    /// * `timeout` - How long each capture waits for a frame, in milliseconds. This sets how often `is_finished` is
+    ///   checked when no frames are arriving
+    ///
+    fn record_to<S: FrameSink>(&self, sink: &mut S, timeout: u32) -> Result<(), RecordError<S::Error>>;
+
+    /// Keep the receiver connected to a named source, reconnecting whenever the connection is lost
+    ///
+    /// The connection is monitored from a background thread, which stops when the receiver is dropped or
+    /// `disable_auto_reconnect` is called. Enabling again replaces the previous source. While the source is missing,
+    /// attempts are spaced out by the backoff set with `with_reconnect_backoff`, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_name` - The source to connect to, matched as described by `FindSource::matches_name`
+    /// * `callback` - Called from the background thread when the connection is lost or reestablished
+    ///
+    fn enable_auto_reconnect<F>(&self, source_name: &str, callback: F)
+    where
+        F: Fn(ReconnectEvent) + Send + 'static;
+
+    /// Wait for a frame without blocking the async runtime
+    ///
+    /// The capture runs on the tokio blocking thread pool, and the future resolves once a frame arrives.
+    /// Dropping the future stops the capture, and any frame captured after that point is freed.
+    #[cfg(feature = "async")]
+    fn recv_async(
+        &self,
+        capture_video: bool,
+        capture_audio: bool,
+        capture_metadata: bool,
+    ) -> impl Future<Output = Result<ReceiveCaptureResult, NdiError>> + Send;
 }
 
 impl ReceiveInstanceExt for Arc<ReceiveInstance> {
@@ -320,7 +1413,7 @@ impl ReceiveInstanceExt for Arc<ReceiveInstance> {
         capture_audio: bool,
         capture_metadata: bool,
         timeout: u32,
-    ) -> Result<ReceiveCaptureResult, ReceiveCaptureError> {
+    ) -> Result<ReceiveCaptureResult, NdiError> {
         let video_data = if capture_video {
             Some(sdk::NDIlib_video_frame_v2_t {
                 xres: 0,
@@ -372,73 +1465,320 @@ impl ReceiveInstanceExt for Arc<ReceiveInstance> {
                 timeout,
             )
         };
-        match captured {
+        let res = match captured {
             sdk::NDIlib_frame_type_video => match video_data {
-                None => Err(ReceiveCaptureError::Failed),
-                Some(video_data) => match self.video_frames.track(video_data) {
-                    None => Err(ReceiveCaptureError::Poisoned),
-                    Some(v) => {
-                        let frame = VideoFrame {
-                            id: v.0,
-                            instance: v.1,
-                            parent: Arc::downgrade(self),
-
-                            width: video_data.xres,
-                            height: video_data.yres,
-
-                            frame_rate_d: video_data.frame_rate_D,
-                            frame_rate_n: video_data.frame_rate_N,
-
-                            timecode: video_data.timecode,
-                            four_cc_type: FourCCType::try_from(video_data.FourCC)?,
-                            frame_format_type: FrameFormatType::try_from(video_data.frame_format_type)?,
-                            timestamp: video_data.timestamp,
-                        };
-                        Ok(ReceiveCaptureResult::Video(frame))
-                    }
-                },
+                None => Err(ReceiveCaptureError::Failed.into()),
+                Some(video_data) => {
+                    let frame = VideoFrame::track(&self.video_frames, Arc::downgrade(self) as _, video_data)?;
+                    update_video_format(&self.video_format, frame.format());
+                    Ok(ReceiveCaptureResult::Video(frame))
+                }
             },
             sdk::NDIlib_frame_type_audio => match audio_data {
-                None => Err(ReceiveCaptureError::Failed),
-                Some(audio_data) => match self.audio_frames.track(audio_data) {
-                    None => Err(ReceiveCaptureError::Poisoned),
-                    Some(v) => {
-                        let frame = AudioFrame {
-                            id: v.0,
-                            instance: v.1,
-                            parent: Arc::downgrade(self),
-
-                            sample_rate: audio_data.sample_rate,
-                            channel_count: audio_data.no_channels,
-                            sample_count: audio_data.no_samples,
-                            timecode: audio_data.timecode,
-                            timestamp: audio_data.timestamp,
-                        };
-                        Ok(ReceiveCaptureResult::Audio(frame))
+                None => Err(ReceiveCaptureError::Failed.into()),
+                Some(audio_data) => {
+                    let frame = AudioFrame::track(&self.audio_frames, Arc::downgrade(self) as _, audio_data)?;
+                    Ok(ReceiveCaptureResult::Audio(frame))
+                }
+            },
+            sdk::NDIlib_frame_type_metadata => match metadata {
+                None => Err(ReceiveCaptureError::Failed.into()),
+                Some(metadata) => {
+                    let frame = from_ndi_metadata(&metadata);
+                    unsafe {
+                        self.sdk_instance.NDIlib_recv_free_metadata.unwrap()(self.instance, &metadata);
                     }
-                },
+                    if let Some(info) = SourceInfo::parse(&frame.xml) {
+                        ndi_debug!("Source product {:?}", info);
+                        if let Ok(mut current) = self.source_info.lock() {
+                            *current = Some(info);
+                        }
+                    }
+                    Ok(ReceiveCaptureResult::Metadata(frame))
+                }
             },
             sdk::NDIlib_frame_type_none => Ok(ReceiveCaptureResult::None),
-            _ => Err(ReceiveCaptureError::Invalid),
+            sdk::NDIlib_frame_type_status_change => {
+                ndi_debug!("Receiver status changed");
+                Ok(ReceiveCaptureResult::StatusChange)
+            }
+            sdk::NDIlib_frame_type_error => {
+                ndi_warn!("Receiver reported an error, the connection may have been lost");
+                Ok(ReceiveCaptureResult::Error)
+            }
+            _ => Err(ReceiveCaptureError::Invalid.into()),
+        };
+
+        if let Err(e) = &res {
+            ndi_warn!("Capture failed with frame type {}: {:?}", captured, e);
+        }
+        res
+    }
+
+    fn wait_for_status_change(&self, timeout: u32) -> Result<ReceiveCaptureResultType, NdiError> {
+        let captured = unsafe {
+            self.sdk_instance.NDIlib_recv_capture_v2.unwrap()(
+                self.instance,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                timeout,
+            )
+        };
+        match captured {
+            sdk::NDIlib_frame_type_none => Ok(ReceiveCaptureResultType::None),
+            sdk::NDIlib_frame_type_status_change => Ok(ReceiveCaptureResultType::StatusChange),
+            sdk::NDIlib_frame_type_error => Ok(ReceiveCaptureResultType::Error),
+            _ => Err(ReceiveCaptureError::Invalid.into()),
+        }
+    }
+
+    fn frames(&self, timeout: u32) -> ReceiveFrames {
+        ReceiveFrames {
+            receiver: self.clone(),
+            timeout,
+        }
+    }
+
+    fn capture(
+        &self,
+        capture_video: bool,
+        capture_audio: bool,
+        capture_metadata: bool,
+    ) -> Result<ReceiveCaptureResult, NdiError> {
+        let mut retries = 0;
+        loop {
+            let res = self.receive_capture(capture_video, capture_audio, capture_metadata, self.capture_timeout)?;
+            match res {
+                ReceiveCaptureResult::None | ReceiveCaptureResult::Error if retries < self.max_retries => retries += 1,
+                res => return Ok(res),
+            }
+        }
+    }
+
+    fn capture_video(&self, timeout: u32) -> Result<Option<VideoFrame>, NdiError> {
+        match self.receive_capture(true, false, false, timeout)? {
+            ReceiveCaptureResult::Video(video) => Ok(Some(video)),
+            ReceiveCaptureResult::Error => Err(NdiError::ConnectionLost),
+            _ => Ok(None),
+        }
+    }
+
+    fn capture_audio(&self, timeout: u32) -> Result<Option<AudioFrame>, NdiError> {
+        match self.receive_capture(false, true, false, timeout)? {
+            ReceiveCaptureResult::Audio(audio) => Ok(Some(audio)),
+            ReceiveCaptureResult::Error => Err(NdiError::ConnectionLost),
+            _ => Ok(None),
+        }
+    }
+
+    fn capture_metadata(&self, timeout: u32) -> Result<Option<MetadataFrame>, NdiError> {
+        match self.receive_capture(false, false, true, timeout)? {
+            ReceiveCaptureResult::Metadata(metadata) => Ok(Some(metadata)),
+            ReceiveCaptureResult::Error => Err(NdiError::ConnectionLost),
+            _ => Ok(None),
         }
     }
+
+    fn capture_any(&self, timeout: u32) -> Result<ReceiveCaptureResult, NdiError> {
+        self.receive_capture(true, true, true, timeout)
+    }
+
+    fn try_receive_video(&self) -> Option<VideoFrame> {
+        self.capture_video(0).ok().flatten()
+    }
+
+    fn capture_video_into(&self, timeout: u32, buffer: &mut [u8]) -> Result<Option<CapturedVideoInfo>, NdiError> {
+        let frame = match self.capture_video(timeout)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        let data_len = match frame.lock_data() {
+            Some(data) => {
+                if data.len() > buffer.len() {
+                    return Err(NdiError::BufferTooSmall(data.len()));
+                }
+                buffer[..data.len()].copy_from_slice(&data);
+                data.len()
+            }
+            None if frame.instance.is_poisoned() => return Err(ReceiveCaptureError::Poisoned.into()),
+            // The frame has no data, so it is still reported, with nothing written
+            None => 0,
+        };
+
+        Ok(Some(CapturedVideoInfo {
+            format: frame.format(),
+            line_stride: frame.line_stride(),
+            data_len,
+            timecode: frame.timecode,
+            timestamp: frame.timestamp,
+        }))
+    }
+
+    fn capture_latest_video(&self, max_drain: usize) -> Result<Option<LatestVideoFrame>, NdiError> {
+        let mut latest = match self.capture_video(0)? {
+            Some(frame) => LatestVideoFrame { frame, dropped: 0 },
+            None => return Ok(None),
+        };
+        while latest.dropped < max_drain {
+            match self.capture_video(0)? {
+                Some(frame) => {
+                    // Replacing the frame returns the older one to the SDK
+                    latest.frame = frame;
+                    latest.dropped += 1;
+                }
+                None => break,
+            }
+        }
+        Ok(Some(latest))
+    }
+
+    fn try_receive_audio(&self) -> Option<AudioFrame> {
+        self.capture_audio(0).ok().flatten()
+    }
+
+    fn try_receive_metadata(&self) -> Option<MetadataFrame> {
+        self.capture_metadata(0).ok().flatten()
+    }
+
+    fn video_frames(&self, timeout: u32) -> ReceiveVideoFrames {
+        ReceiveVideoFrames {
+            receiver: self.clone(),
+            timeout,
+        }
+    }
+
+    fn create_framesync(&self) -> Result<Arc<FrameSyncInstance>, FrameSyncCreateError> {
+        create_framesync_instance(self.clone())
+    }
+
+    fn record_to<S: FrameSink>(&self, sink: &mut S, timeout: u32) -> Result<(), RecordError<S::Error>> {
+        record(self, sink, timeout)
+    }
+
+    fn enable_auto_reconnect<F>(&self, source_name: &str, callback: F)
+    where
+        F: Fn(ReconnectEvent) + Send + 'static,
+    {
+        let handle = spawn_reconnect(
+            Arc::downgrade(self),
+            ReconnectSource::Named(source_name.to_string()),
+            self.reconnect_backoff,
+            callback,
+        );
+        if let Ok(mut reconnect) = self.reconnect.lock() {
+            *reconnect = Some(handle);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn recv_async(
+        &self,
+        capture_video: bool,
+        capture_audio: bool,
+        capture_metadata: bool,
+    ) -> impl Future<Output = Result<ReceiveCaptureResult, NdiError>> + Send {
+        let receiver = self.clone();
+        async move {
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let _guard = CancelOnDrop(cancelled.clone());
+
+            let handle = tokio::task::spawn_blocking(move || loop {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Ok(ReceiveCaptureResult::None);
+                }
+                // Use a short timeout, so that cancellation is noticed promptly
+                match receiver.receive_capture(capture_video, capture_audio, capture_metadata, 100) {
+                    Ok(ReceiveCaptureResult::None) => {}
+                    res => return res,
+                }
+            });
+
+            match handle.await {
+                Ok(res) => res,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+    }
+}
+
+/// An iterator of captured frames, created by `frames()`
+pub struct ReceiveFrames {
+    receiver: Arc<ReceiveInstance>,
+    timeout: u32,
+}
+impl Iterator for ReceiveFrames {
+    type Item = ReceiveCaptureResult;
+
+    fn next(&mut self) -> Option<ReceiveCaptureResult> {
+        self.receiver.receive_capture(true, true, true, self.timeout).ok()
+    }
+}
+
+/// An iterator of captured video frames, created by `video_frames()`
+pub struct ReceiveVideoFrames {
+    receiver: Arc<ReceiveInstance>,
+    timeout: u32,
+}
+impl Iterator for ReceiveVideoFrames {
+    type Item = VideoFrame;
+
+    fn next(&mut self) -> Option<VideoFrame> {
+        loop {
+            match self.receiver.receive_capture(true, false, false, self.timeout) {
+                Err(_) | Ok(ReceiveCaptureResult::Error) => return None,
+                Ok(ReceiveCaptureResult::Video(video)) => return Some(video),
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+struct CancelOnDrop(Arc<AtomicBool>);
+#[cfg(feature = "async")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 }
 
 #[derive(Debug)]
 pub enum ReceiveCreateError {
+    InvalidName,
+    InvalidSource,
     Failed,
 }
 
-#[derive(Debug)]
+/// How much of a source to receive. This is fixed when a receiver is created, see
+/// `ReceiveInstance::recreate_with_bandwidth` for switching
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum ReceiveBandwidth {
     MetadataOnly = sdk::NDIlib_recv_bandwidth_metadata_only as isize,
     AudioOnly = sdk::NDIlib_recv_bandwidth_audio_only as isize,
+    /// Receive the source's low resolution proxy stream, intended for previews and constrained links
+    ///
+    /// This is the only way the SDK offers to pick a smaller stream. There is no standard way to ask for a particular
+    /// resolution or frame rate, but sources which support it can be asked through
+    /// `ReceiveInstance::add_connection_metadata`, using whatever XML the source documents.
     Lowest = sdk::NDIlib_recv_bandwidth_lowest as isize,
+    /// Receive the full quality stream
     Highest = sdk::NDIlib_recv_bandwidth_highest as isize,
 }
 
-#[derive(Debug)]
+/// The color formats to receive video in
+///
+/// The SDK picks between the two formats depending on whether the source has an alpha channel, so the actual format
+/// of each frame is given by `VideoFrame::four_cc()`. `Fastest` may return any format.
+///
+/// There is no negotiation with the source, as the SDK converts to whichever format was requested, so a list of
+/// preferred formats would always get the first. To accept several formats and avoid the cost of converting, eg UYVY
+/// from 4K sources, receive with `Fastest`, check `four_cc()` on each frame, and convert only the formats which aren't
+/// usable directly with `convert::to_rgba`.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum ReceiveColorFormat {
+    /// Whatever the source sends, with no conversion. Sources without alpha are usually UYVY, and sources with alpha
+    /// are UYVA, which keeps the alpha as a separate plane rather than it being converted to BGRA or RGBA
     Fastest = sdk::NDIlib_recv_color_format_fastest as isize,
     BgrxBgra = sdk::NDIlib_recv_color_format_BGRX_BGRA as isize, // No alpha channel: BGRX, Alpha channel: BGRA
     UyvyBgra = sdk::NDIlib_recv_color_format_UYVY_BGRA as isize, // No alpha channel: UYVY, Alpha channel: BGRA
@@ -446,38 +1786,157 @@ pub enum ReceiveColorFormat {
     UyvyRgba = sdk::NDIlib_recv_color_format_UYVY_RGBA as isize, // No alpha channel: UYVY, Alpha channel: RGBA
 }
 
-pub fn create_receive_instance(
+/// The default timeout used by `ReceiveInstanceExt::capture`, in milliseconds
+pub const DEFAULT_CAPTURE_TIMEOUT: u32 = 1000;
+
+/// Options for creating a ReceiveInstance
+pub struct ReceiveInstanceBuilder {
     sdk_instance: Arc<NDIHandle>,
     bandwidth: ReceiveBandwidth,
     color_format: ReceiveColorFormat,
-) -> Result<Arc<ReceiveInstance>, ReceiveCreateError> {
-    let props = sdk::NDIlib_recv_create_v3_t {
-        source_to_connect_to: sdk::NDIlib_source_t {
-            p_ndi_name: null(),
-            __bindgen_anon_1: sdk::NDIlib_source_t__bindgen_ty_1 { p_url_address: null() },
-        },
-        color_format: color_format as u32,
-        bandwidth: bandwidth as i32,
-        allow_video_fields: false,
-        p_ndi_recv_name: null(),
-    };
-
-    let instance = unsafe { sdk_instance.NDIlib_recv_create_v3.unwrap()(&props) };
-
-    if instance.is_null() {
-        Err(ReceiveCreateError::Failed)
-    } else {
-        Ok(Arc::new(ReceiveInstance {
-            sdk_instance,
-            instance,
-            video_frames: ReceiveDataStore {
-                data: Mutex::new(HashMap::new()),
-                next_id: AtomicUsize::new(0),
-            },
-            audio_frames: ReceiveDataStore {
-                data: Mutex::new(HashMap::new()),
-                next_id: AtomicUsize::new(0),
+    name: Option<String>,
+    source: Option<FindSource>,
+    allow_video_fields: bool,
+    capture_timeout: u32,
+    max_retries: u32,
+    reconnect_backoff: Option<ReconnectBackoff>,
+}
+impl ReceiveInstanceBuilder {
+    /// The bandwidth to receive at. Defaults to `ReceiveBandwidth::Highest`
+    pub fn with_bandwidth(mut self, bandwidth: ReceiveBandwidth) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+    /// The color formats to receive video in. Defaults to `ReceiveColorFormat::UyvyBgra`
+    pub fn with_color_format(mut self, color_format: ReceiveColorFormat) -> Self {
+        self.color_format = color_format;
+        self
+    }
+    /// The name of the receiver, as it will appear in the connection list of the source, eg `VJ Rig - Screen 2`
+    ///
+    /// Without a name the SDK identifies the receiver by the name of the process
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+    /// A source to connect to as soon as the receiver is created
+    pub fn with_source(mut self, source: FindSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+    /// Whether interlaced video should be received as separate fields. Defaults to false
+    ///
+    /// When false, the SDK combines fields into `FrameFormatType::Interlaced` frames, which is usually what display
+    /// code wants. When true, frames are delivered as `Field0` and `Field1` as they arrive, for custom deinterlacing
+    /// with lower latency.
+    pub fn with_allow_video_fields(mut self, allow_video_fields: bool) -> Self {
+        self.allow_video_fields = allow_video_fields;
+        self
+    }
+    /// How long `capture` waits for each attempt, in milliseconds. Defaults to `DEFAULT_CAPTURE_TIMEOUT`
+    pub fn with_capture_timeout(mut self, capture_timeout: u32) -> Self {
+        self.capture_timeout = capture_timeout;
+        self
+    }
+    /// How many more times `capture` tries when an attempt times out or errors. Defaults to 0
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+    /// Keep retrying the source passed to `connect` whenever the receiver isn't connected, spacing out the attempts
+    /// with a backoff. Defaults to none, which leaves reconnecting to the SDK
+    ///
+    /// The source given by `with_source`, `connect` or `connect_by_name` is looked for by name on each attempt, from
+    /// a background thread which stops when the receiver is dropped or `disable_auto_reconnect` is called.
+    /// `enable_auto_reconnect` uses the same backoff, and replaces this thread.
+    pub fn with_reconnect_backoff(mut self, backoff: ReconnectBackoff) -> Self {
+        self.reconnect_backoff = Some(backoff);
+        self
+    }
+    pub fn build(self) -> Result<Arc<ReceiveInstance>, ReceiveCreateError> {
+        let name = match &self.name {
+            None => None,
+            Some(name) => Some(CString::new(name.as_bytes()).map_err(|_| ReceiveCreateError::InvalidName)?),
+        };
+        let source = match &self.source {
+            None => None,
+            Some(source) => Some(to_ndi_source(source).map_err(|_| ReceiveCreateError::InvalidSource)?),
+        };
+
+        let props = sdk::NDIlib_recv_create_v3_t {
+            source_to_connect_to: match &source {
+                Some(source) => source.2,
+                None => sdk::NDIlib_source_t {
+                    p_ndi_name: null(),
+                    __bindgen_anon_1: sdk::NDIlib_source_t__bindgen_ty_1 { p_url_address: null() },
+                },
             },
-        }))
+            color_format: self.color_format as u32,
+            bandwidth: self.bandwidth as i32,
+            allow_video_fields: self.allow_video_fields,
+            p_ndi_recv_name: name.as_ref().map_or(null(), |n| n.as_ptr()),
+        };
+
+        let instance = unsafe { self.sdk_instance.NDIlib_recv_create_v3.unwrap()(&props) };
+
+        if instance.is_null() {
+            Err(ReceiveCreateError::Failed)
+        } else {
+            let receiver = Arc::new(ReceiveInstance {
+                sdk_instance: self.sdk_instance,
+                instance,
+                video_frames: ReceiveDataStore::new(),
+                audio_frames: ReceiveDataStore::new(),
+                tally: Mutex::new(Tally::default()),
+                source: Mutex::new(self.source),
+                video_format: Mutex::new(None),
+                source_info: Mutex::new(None),
+                bandwidth: self.bandwidth,
+                color_format: self.color_format,
+                name: self.name,
+                allow_video_fields: self.allow_video_fields,
+                capture_timeout: self.capture_timeout,
+                max_retries: self.max_retries,
+                reconnect_backoff: self.reconnect_backoff,
+                reconnect: Mutex::new(None),
+            });
+            if let Some(backoff) = self.reconnect_backoff {
+                let handle = spawn_reconnect(
+                    Arc::downgrade(&receiver),
+                    ReconnectSource::Current,
+                    Some(backoff),
+                    |_| {},
+                );
+                if let Ok(mut reconnect) = receiver.reconnect.lock() {
+                    *reconnect = Some(handle);
+                }
+            }
+            Ok(receiver)
+        }
+    }
+}
+
+pub fn create_receive_builder(sdk_instance: Arc<NDIHandle>) -> ReceiveInstanceBuilder {
+    ReceiveInstanceBuilder {
+        sdk_instance,
+        bandwidth: ReceiveBandwidth::Highest,
+        color_format: ReceiveColorFormat::UyvyBgra,
+        name: None,
+        source: None,
+        allow_video_fields: false,
+        capture_timeout: DEFAULT_CAPTURE_TIMEOUT,
+        max_retries: 0,
+        reconnect_backoff: None,
     }
 }
+
+pub fn create_receive_instance(
+    sdk_instance: Arc<NDIHandle>,
+    bandwidth: ReceiveBandwidth,
+    color_format: ReceiveColorFormat,
+) -> Result<Arc<ReceiveInstance>, ReceiveCreateError> {
+    create_receive_builder(sdk_instance)
+        .with_bandwidth(bandwidth)
+        .with_color_format(color_format)
+        .build()
+}
@@ -0,0 +1,397 @@
+use crate::finder::{create_find_instance, FindInstance, FindSource};
+use crate::receive::ReceiveInstance;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the connection is checked
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// How long to wait for the source to reappear on each attempt
+const FIND_TIMEOUT: u32 = 1000;
+/// How long a new connection is given to establish before it is considered lost
+const CONNECT_GRACE: Duration = Duration::from_secs(3);
+
+/// How long to wait after each failed reconnection attempt, doubling the wait every time up to a limit
+///
+/// Without a backoff, each attempt waits up to a second for the source to appear, then half a second before the next
+/// one, for as long as the source is missing. With a backoff, the list of sources is checked without waiting, and the
+/// attempts are spaced out by `delay`, which saves CPU and battery on unreliable networks. An attempt fails when the
+/// source isn't found, or when the receiver still isn't connected 3 seconds after connecting to it. The wait goes
+/// back to `initial` once reconnected.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ReconnectBackoff {
+    /// The wait after the first failed attempt
+    pub initial: Duration,
+    /// The longest wait between attempts
+    pub max: Duration,
+}
+impl ReconnectBackoff {
+    pub fn new(initial: Duration, max: Duration) -> ReconnectBackoff {
+        ReconnectBackoff { initial, max }
+    }
+
+    /// The wait after the given number of consecutive failed attempts
+    pub fn delay(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(31);
+        self.initial.checked_mul(factor).unwrap_or(self.max).min(self.max)
+    }
+}
+impl Default for ReconnectBackoff {
+    /// Starts at 100ms, and stops doubling at 30 seconds
+    fn default() -> ReconnectBackoff {
+        ReconnectBackoff::new(Duration::from_millis(100), Duration::from_secs(30))
+    }
+}
+
+/// A change in the connection state of a receiver with auto reconnect enabled
+#[derive(Debug, Clone)]
+pub enum ReconnectEvent {
+    /// The connection to the source was lost, and the receiver is trying to reconnect
+    Disconnected,
+    /// The source was found again and the receiver reconnected to it
+    Reconnected(FindSource),
+}
+
+/// Stops the reconnect thread when dropped
+pub(crate) struct ReconnectHandle(Arc<AtomicBool>);
+impl Drop for ReconnectHandle {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The source a reconnect thread keeps the receiver connected to
+pub(crate) enum ReconnectSource {
+    /// A source found by name, set by `enable_auto_reconnect`
+    Named(String),
+    /// Whichever source was last passed to `connect`, if any
+    Current,
+}
+
+/// What the reconnect loop needs from the receiver and the clock, so the loop can be tested without the SDK
+trait ReconnectHost {
+    /// The name of the source to stay connected to, or None if there is nothing to reconnect to
+    fn source_name(&self) -> Option<String>;
+    fn is_connected(&self) -> bool;
+    /// Look for the source, waiting up to `timeout` milliseconds for it to appear
+    fn find(&self, name: &str, timeout: u32) -> Option<FindSource>;
+    /// Connect to the source, returning whether the connection was started
+    fn connect(&self, source: &FindSource) -> bool;
+    fn now(&self) -> Instant;
+    /// Sleep, returning false if the loop should stop instead
+    fn sleep(&self, duration: Duration) -> bool;
+}
+
+/// The real host, which holds the receiver weakly so the thread doesn't keep it alive
+struct ReceiverHost {
+    receiver: Weak<ReceiveInstance>,
+    source: ReconnectSource,
+    finder: FindInstance,
+    stopped: Arc<AtomicBool>,
+}
+impl ReconnectHost for ReceiverHost {
+    fn source_name(&self) -> Option<String> {
+        match &self.source {
+            ReconnectSource::Named(name) => Some(name.clone()),
+            ReconnectSource::Current => self.receiver.upgrade()?.target_source().map(|s| s.name),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.receiver.upgrade().is_some_and(|r| r.is_connected())
+    }
+
+    fn find(&self, name: &str, timeout: u32) -> Option<FindSource> {
+        self.finder.wait_for_source(timeout, |s| s.matches_name(name))
+    }
+
+    fn connect(&self, source: &FindSource) -> bool {
+        match self.receiver.upgrade() {
+            Some(r) => !self.stopped.load(Ordering::Relaxed) && r.connect(Some(source)).is_ok(),
+            None => false,
+        }
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> bool {
+        // Wake regularly to notice being stopped
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.stopped.load(Ordering::Relaxed) || self.receiver.strong_count() == 0 {
+                return false;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_millis(0) {
+                return true;
+            }
+            thread::sleep(remaining.min(CHECK_INTERVAL));
+        }
+    }
+}
+
+/// Keep the host connected until it stops
+fn run_reconnect<H, F>(host: &H, backoff: Option<ReconnectBackoff>, callback: F)
+where
+    H: ReconnectHost,
+    F: Fn(ReconnectEvent),
+{
+    // Whether the receiver was last reported as connected. It starts connected, so a receiver which never connects is
+    // reported as disconnected once the grace period ends
+    let mut connected = true;
+    let mut failures = 0;
+    let mut grace_until = host.now() + CONNECT_GRACE;
+    // The source of an attempt which is waiting to connect
+    let mut pending: Option<FindSource> = None;
+    let mut last_name = None;
+
+    loop {
+        let name = host.source_name();
+        if name != last_name {
+            // A new source was connected to directly, so give it time to connect
+            connected = true;
+            failures = 0;
+            grace_until = host.now() + CONNECT_GRACE;
+            pending = None;
+            last_name = name.clone();
+        }
+        let name = match name {
+            Some(name) => name,
+            None => {
+                if !host.sleep(CHECK_INTERVAL) {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        if host.is_connected() {
+            connected = true;
+            failures = 0;
+            if let Some(source) = pending.take() {
+                ndi_info!("Reconnected to {}", source.name);
+                callback(ReconnectEvent::Reconnected(source));
+            }
+            if !host.sleep(CHECK_INTERVAL) {
+                return;
+            }
+            continue;
+        }
+
+        let now = host.now();
+        if now < grace_until {
+            if !host.sleep((grace_until - now).min(CHECK_INTERVAL)) {
+                return;
+            }
+            continue;
+        }
+
+        if connected {
+            connected = false;
+            ndi_warn!("Lost connection to {}, reconnecting", name);
+            callback(ReconnectEvent::Disconnected);
+        }
+
+        let retry_wait = |failures| match &backoff {
+            Some(backoff) => backoff.delay(failures),
+            None => CHECK_INTERVAL,
+        };
+        if pending.take().is_some() {
+            // The last attempt found the source but never connected
+            failures += 1;
+            if !host.sleep(retry_wait(failures)) {
+                return;
+            }
+            continue;
+        }
+
+        // With a backoff the wait between attempts is the delay, so don't also wait for the source
+        let find_timeout = if backoff.is_some() { 0 } else { FIND_TIMEOUT };
+        match host.find(&name, find_timeout) {
+            Some(source) if host.connect(&source) => {
+                pending = Some(source);
+                grace_until = host.now() + CONNECT_GRACE;
+            }
+            _ => {
+                failures += 1;
+                if !host.sleep(retry_wait(failures)) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn spawn_reconnect<F>(
+    receiver: Weak<ReceiveInstance>,
+    source: ReconnectSource,
+    backoff: Option<ReconnectBackoff>,
+    callback: F,
+) -> ReconnectHandle
+where
+    F: Fn(ReconnectEvent) + Send + 'static,
+{
+    let stopped = Arc::new(AtomicBool::new(false));
+    let handle = ReconnectHandle(stopped.clone());
+
+    thread::spawn(move || {
+        let finder = match receiver.upgrade() {
+            Some(r) => create_find_instance(r.sdk_instance.clone(), true),
+            None => None,
+        };
+        let finder = match finder {
+            Some(f) => f,
+            None => {
+                ndi_warn!("Not reconnecting, the finder could not be created");
+                return;
+            }
+        };
+
+        let host = ReceiverHost {
+            receiver,
+            source,
+            finder,
+            stopped,
+        };
+        run_reconnect(&host, backoff, callback);
+    });
+
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    /// A receiver on a simulated clock, which runs until `end`
+    struct FakeHost {
+        start: Instant,
+        elapsed: Cell<Duration>,
+        end: Duration,
+        /// When the source starts being listed, if ever
+        listed_after: Option<Duration>,
+        /// Whether connecting to the source works
+        connects: bool,
+        connected: Cell<bool>,
+        /// When each attempt to find the source was made
+        finds: RefCell<Vec<Duration>>,
+    }
+    impl FakeHost {
+        fn new(end_secs: u64, listed_after: Option<Duration>, connects: bool) -> FakeHost {
+            FakeHost {
+                start: Instant::now(),
+                elapsed: Cell::new(Duration::from_millis(0)),
+                end: Duration::from_secs(end_secs),
+                listed_after,
+                connects,
+                connected: Cell::new(false),
+                finds: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn listed(&self) -> bool {
+            self.listed_after.is_some_and(|after| self.elapsed.get() >= after)
+        }
+
+        /// The milliseconds between attempts
+        fn gaps(&self) -> Vec<u128> {
+            let finds = self.finds.borrow();
+            finds.windows(2).map(|w| (w[1] - w[0]).as_millis()).collect()
+        }
+    }
+    impl ReconnectHost for FakeHost {
+        fn source_name(&self) -> Option<String> {
+            Some("Source".to_string())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.connected.get()
+        }
+
+        fn find(&self, _name: &str, timeout: u32) -> Option<FindSource> {
+            self.finds.borrow_mut().push(self.elapsed.get());
+            if !self.listed() {
+                self.sleep(Duration::from_millis(timeout as u64));
+            }
+            if self.listed() {
+                Some(FindSource::from_url("MACHINE (Source)", "127.0.0.1:5961"))
+            } else {
+                None
+            }
+        }
+
+        fn connect(&self, _source: &FindSource) -> bool {
+            self.connected.set(self.connects);
+            true
+        }
+
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+
+        fn sleep(&self, duration: Duration) -> bool {
+            self.elapsed.set(self.elapsed.get() + duration);
+            self.elapsed.get() < self.end
+        }
+    }
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = ReconnectBackoff::new(Duration::from_millis(100), Duration::from_millis(300));
+        let delays: Vec<_> = (1..5).map(|failures| backoff.delay(failures).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 300, 300]);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn attempts_follow_the_backoff() {
+        let host = FakeHost::new(10, None, true);
+        run_reconnect(&host, Some(ReconnectBackoff::default()), |_| {});
+        // The first attempt is made once the initial connection's grace period ends
+        assert_eq!(host.finds.borrow()[0], CONNECT_GRACE);
+        assert_eq!(host.gaps(), [100, 200, 400, 800, 1600, 3200]);
+    }
+
+    #[test]
+    fn attempts_stop_growing_at_the_max() {
+        let host = FakeHost::new(5, None, true);
+        let backoff = ReconnectBackoff::new(Duration::from_millis(100), Duration::from_millis(300));
+        run_reconnect(&host, Some(backoff), |_| {});
+        assert_eq!(host.gaps()[..5], [100, 200, 300, 300, 300]);
+    }
+
+    #[test]
+    fn connections_which_never_connect_are_failures() {
+        // The source is listed but connecting to it never works, so each attempt fails once its grace period ends
+        let host = FakeHost::new(13, Some(Duration::from_millis(0)), false);
+        run_reconnect(&host, Some(ReconnectBackoff::default()), |_| {});
+        assert_eq!(host.gaps(), [3100, 3200, 3400]);
+    }
+
+    #[test]
+    fn reconnecting_resets_the_backoff() {
+        let host = FakeHost::new(10, Some(Duration::from_millis(4000)), true);
+        let events = RefCell::new(Vec::new());
+        run_reconnect(&host, Some(ReconnectBackoff::default()), |event| {
+            events.borrow_mut().push(event)
+        });
+        assert_eq!(host.gaps(), [100, 200, 400, 800]);
+        let events = events.borrow();
+        assert!(matches!(
+            events[..],
+            [ReconnectEvent::Disconnected, ReconnectEvent::Reconnected(_)]
+        ));
+    }
+
+    #[test]
+    fn attempts_without_a_backoff() {
+        // Each attempt waits a second for the source, then half a second before the next
+        let host = FakeHost::new(8, None, true);
+        run_reconnect(&host, None, |_| {});
+        assert_eq!(host.gaps(), [1500, 1500, 1500]);
+    }
+}
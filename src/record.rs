@@ -0,0 +1,59 @@
+use crate::receive::{
+    AudioFrame, MetadataFrame, ReceiveCaptureResult, ReceiveInstance, ReceiveInstanceExt, VideoFrame,
+};
+use crate::NdiError;
+use std::sync::Arc;
+
+/// Something which captured frames can be written to, such as a muxer writing to a file
+///
+/// Frames are written in the order they are received. Each frame carries its timecode and timestamp, which should be
+/// used to keep audio and video in sync.
+pub trait FrameSink {
+    type Error;
+
+    fn write_video(&mut self, frame: &VideoFrame) -> Result<(), Self::Error>;
+
+    fn write_audio(&mut self, frame: &AudioFrame) -> Result<(), Self::Error>;
+
+    /// Metadata frames are ignored unless this is implemented
+    fn write_metadata(&mut self, _frame: &MetadataFrame) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Whether recording should stop. This is checked after each frame and each capture timeout
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+/// An error which stopped a recording
+#[derive(Debug)]
+pub enum RecordError<E> {
+    /// Capturing from the receiver failed
+    Capture(NdiError),
+    /// The sink failed to write a frame
+    Sink(E),
+}
+
+pub(crate) fn record<S: FrameSink>(
+    receiver: &Arc<ReceiveInstance>,
+    sink: &mut S,
+    timeout: u32,
+) -> Result<(), RecordError<S::Error>> {
+    while !sink.is_finished() {
+        match receiver.receive_capture(true, true, true, timeout) {
+            Err(e) => return Err(RecordError::Capture(e)),
+            // A capture error is usually a dropped connection, which the SDK recovers from by itself
+            Ok(ReceiveCaptureResult::None)
+            | Ok(ReceiveCaptureResult::StatusChange)
+            | Ok(ReceiveCaptureResult::Error) => {}
+            Ok(ReceiveCaptureResult::Video(video)) => sink.write_video(&video).map_err(RecordError::Sink)?,
+            Ok(ReceiveCaptureResult::Audio(audio)) => sink.write_audio(&audio).map_err(RecordError::Sink)?,
+            Ok(ReceiveCaptureResult::Metadata(metadata)) => {
+                sink.write_metadata(&metadata).map_err(RecordError::Sink)?
+            }
+        }
+    }
+
+    Ok(())
+}
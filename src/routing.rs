@@ -0,0 +1,72 @@
+use crate::finder::FindSource;
+use crate::util::to_ndi_source;
+use crate::{sdk, NDIHandle, NdiError};
+use std::ffi::CString;
+use std::ptr::null;
+use std::sync::Arc;
+
+unsafe impl Send for RoutingInstance {}
+unsafe impl Sync for RoutingInstance {}
+/// A virtual source which forwards another source under its own name
+///
+/// Receivers connect to the routing source as normal, and are switched over when the routed source changes, so a
+/// router can present stable names downstream while swapping the inputs behind them. No video passes through this
+/// process, as receivers are redirected to the routed source directly.
+pub struct RoutingInstance {
+    sdk_instance: Arc<NDIHandle>,
+    instance: sdk::NDIlib_routing_instance_t,
+}
+impl Drop for RoutingInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.sdk_instance.NDIlib_routing_destroy.unwrap()(self.instance);
+        }
+    }
+}
+impl RoutingInstance {
+    /// Route a source to receivers of this one
+    pub fn change(&self, source: &FindSource) -> Result<(), NdiError> {
+        let source = to_ndi_source(source).map_err(|_| NdiError::InvalidSource)?;
+        if unsafe { self.sdk_instance.NDIlib_routing_change.unwrap()(self.instance, &source.2) } {
+            Ok(())
+        } else {
+            Err(NdiError::SdkCall)
+        }
+    }
+    /// Stop routing, so receivers of this source get no video
+    pub fn clear(&self) -> bool {
+        unsafe { self.sdk_instance.NDIlib_routing_clear.unwrap()(self.instance) }
+    }
+}
+
+#[derive(Debug)]
+pub enum RoutingCreateError {
+    InvalidName,
+    InvalidGroups,
+    Failed,
+}
+
+pub fn create_routing_instance(
+    sdk_instance: Arc<NDIHandle>,
+    name: String,
+    groups: Option<String>,
+) -> Result<RoutingInstance, RoutingCreateError> {
+    let name2 = CString::new(name.as_bytes()).map_err(|_| RoutingCreateError::InvalidName)?;
+    let groups2 = match groups {
+        None => None,
+        Some(groups) => Some(CString::new(groups.as_bytes()).map_err(|_| RoutingCreateError::InvalidGroups)?),
+    };
+
+    let props = sdk::NDIlib_routing_create_t {
+        p_ndi_name: name2.as_ptr(),
+        p_groups: groups2.as_ref().map_or(null(), |g| g.as_ptr()),
+    };
+
+    let instance = unsafe { sdk_instance.NDIlib_routing_create.unwrap()(&props) };
+
+    if instance.is_null() {
+        Err(RoutingCreateError::Failed)
+    } else {
+        Ok(RoutingInstance { sdk_instance, instance })
+    }
+}
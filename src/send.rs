@@ -1,8 +1,20 @@
-use crate::{sdk, NDIHandle};
+use crate::convert::{frame_data_len, min_stride};
+use crate::finder::FindSource;
+use crate::receive::{FourCCType, MetadataFrame, Tally};
+use crate::util::{deinterleave_audio, to_ndi_metadata, to_ndi_source};
+use crate::{sdk, NDIHandle, NdiError};
 use std::ffi::CString;
 use std::ptr::{null, null_mut};
+use std::slice;
 use std::sync::Arc;
 
+/// The timecode which tells the SDK to generate one, which is the default for frames built by this module
+///
+/// Synthesized timecodes start from the current time and advance by the duration of each frame. With `clock_video` or
+/// `clock_audio` enabled, frames are sent at that rate, so the timecodes track real time. Without clocking they still
+/// advance by one frame duration per frame, however quickly frames are sent.
+pub const TIMECODE_SYNTHESIZE: i64 = sdk::NDIlib_send_timecode_synthesize;
+
 unsafe impl Send for SendInstance {}
 pub struct SendInstance {
     sdk_instance: Arc<NDIHandle>,
@@ -21,21 +33,33 @@ impl Drop for SendInstance {
     }
 }
 impl SendInstance {
+    /// The SDK handle of the sender. See the `raw` module for the safety rules
+    #[cfg(feature = "unsafe-raw")]
+    pub fn raw_instance(&self) -> sdk::NDIlib_send_instance_t {
+        self.instance
+    }
+    /// Send a video frame, blocking until the SDK has finished with it
+    ///
+    /// When the sender was created with `clock_video`, this also blocks until it is time for the frame to be sent.
     pub fn send_video(&mut self, frame: NDISendVideoFrame) {
         unsafe {
-            // TODO - is this going to be a race condition?
-            //            self.in_flight_video = Some(frame);
-            //            self.sdk_instance.NDIlib_send_send_video_v2.unwrap()(self.instance, &self.in_flight_video.as_ref().unwrap().instance);
             self.sdk_instance.NDIlib_send_send_video_v2.unwrap()(self.instance, &frame.instance);
-            self.in_flight_video = Some(frame);
         }
+        // A synchronous send also completes any async frame, so nothing is in flight any more
+        self.in_flight_video = None;
     }
+    /// Send a video frame without waiting for the SDK to finish with it
+    ///
+    /// The SDK reads the frame in the background until the next call to `send_video`, `send_video_async` or
+    /// `send_video_flush`, so the frame is kept here until then and dropped afterwards. This allows the next frame to
+    /// be prepared while the previous one is being sent.
     pub fn send_video_async(&mut self, frame: NDISendVideoFrame) {
         unsafe {
             self.sdk_instance.NDIlib_send_send_video_async_v2.unwrap()(self.instance, &frame.instance);
             self.in_flight_video = Some(frame);
         }
     }
+    /// Wait for any async frame to finish sending, and release it
     pub fn send_video_flush(&mut self) {
         unsafe {
             self.sdk_instance.NDIlib_send_send_video_async_v2.unwrap()(self.instance, null());
@@ -47,6 +71,68 @@ impl SendInstance {
             self.sdk_instance.NDIlib_send_send_audio_v2.unwrap()(self.instance, &frame.instance);
         }
     }
+    /// Send a metadata frame to all connected receivers
+    ///
+    /// Returns false if the metadata could not be converted to a C string
+    pub fn send_metadata(&mut self, frame: &MetadataFrame) -> bool {
+        if let Ok(metadata) = to_ndi_metadata(frame) {
+            unsafe {
+                self.sdk_instance.NDIlib_send_send_metadata.unwrap()(self.instance, &metadata.1);
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+    /// Get the tally state of the source, as set by the receivers that are connected to it
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The time to wait for the tally to change, in milliseconds. Use 0 to return immediately
+    ///
+    pub fn get_tally(&self, timeout: u32) -> Tally {
+        let mut tally = sdk::NDIlib_tally_t {
+            on_program: false,
+            on_preview: false,
+        };
+        unsafe {
+            self.sdk_instance.NDIlib_send_get_tally.unwrap()(self.instance, &mut tally, timeout);
+        }
+
+        Tally {
+            on_program: tally.on_program,
+            on_preview: tally.on_preview,
+        }
+    }
+    /// Get the number of receivers connected to the source
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait for a connection, in milliseconds. This blocks until there is at
+    ///   least one connection or the timeout expires, so use 0 to return immediately
+    ///
+    pub fn get_no_connections(&self, timeout: u32) -> i32 {
+        unsafe { self.sdk_instance.NDIlib_send_get_no_connections.unwrap()(self.instance, timeout) }
+    }
+    /// Set the source receivers should switch to if this source goes offline, eg a backup encoder
+    ///
+    /// Receivers switch back once this source returns. Use None to remove the failover source
+    pub fn set_failover(&self, source: Option<&FindSource>) -> Result<(), NdiError> {
+        match source {
+            None => unsafe {
+                self.sdk_instance.NDIlib_send_set_failover.unwrap()(self.instance, null());
+            },
+            Some(source) => {
+                // The SDK copies the source, so the strings only need to outlive the call
+                let source = to_ndi_source(source).map_err(|_| NdiError::InvalidSource)?;
+                unsafe {
+                    self.sdk_instance.NDIlib_send_set_failover.unwrap()(self.instance, &source.2);
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 pub enum FrameFormatType {
@@ -56,7 +142,7 @@ pub enum FrameFormatType {
     Field1 = sdk::NDIlib_frame_format_type_field_1 as isize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum SendColorFormat {
     Uyvy = sdk::NDIlib_FourCC_type_UYVY as isize,
     Yv12 = sdk::NDIlib_FourCC_type_YV12 as isize,
@@ -68,11 +154,89 @@ pub enum SendColorFormat {
     Rgbx = sdk::NDIlib_FourCC_type_RGBX as isize,
     Uyva = sdk::NDIlib_FourCC_type_UYVA as isize,
 }
+impl SendColorFormat {
+    /// The layout of the data, which is the same as the received format with the same FourCC
+    pub fn four_cc(self) -> FourCCType {
+        match self {
+            SendColorFormat::Uyvy => FourCCType::UYVY,
+            SendColorFormat::Yv12 => FourCCType::YV12,
+            SendColorFormat::Nv12 => FourCCType::NV12,
+            SendColorFormat::I420 => FourCCType::I420,
+            SendColorFormat::Bgra => FourCCType::BGRA,
+            SendColorFormat::Bgrx => FourCCType::BGRX,
+            SendColorFormat::Rgba => FourCCType::RGBA,
+            SendColorFormat::Rgbx => FourCCType::RGBX,
+            SendColorFormat::Uyva => FourCCType::UYVA,
+        }
+    }
+}
+
+/// A block of 16 bytes, to give buffers 16 byte alignment
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+struct AlignedBlock([u8; 16]);
+
+/// A writable buffer for a video frame to send, laid out the way the SDK handles best
+///
+/// The start of the buffer, and the start of every row, is 16 byte aligned. For the planar formats the stride is a
+/// multiple of 32, so the rows of the half width chroma planes are aligned too. The chroma planes follow the Y plane
+/// directly, in the same layout as received frames. Bytes between the end of each row and the stride are padding.
+#[derive(Clone)]
+pub struct SendVideoBuffer {
+    blocks: Vec<AlignedBlock>,
+    len: usize,
+    width: i32,
+    height: i32,
+    line_stride: usize,
+    format: SendColorFormat,
+}
+impl SendVideoBuffer {
+    /// Allocate a zeroed buffer for a frame
+    pub fn alloc(width: i32, height: i32, format: SendColorFormat) -> SendVideoBuffer {
+        let four_cc = format.four_cc();
+        let (w, h) = (width.max(0) as usize, height.max(0) as usize);
+        let alignment = match four_cc {
+            FourCCType::NV12 | FourCCType::I420 | FourCCType::YV12 => 32,
+            _ => 16,
+        };
+        let line_stride = min_stride(four_cc, w).div_ceil(alignment) * alignment;
+        let len = frame_data_len(four_cc, w, h, line_stride);
+
+        SendVideoBuffer {
+            blocks: vec![AlignedBlock([0; 16]); len.div_ceil(16)],
+            len,
+            width,
+            height,
+            line_stride,
+            format,
+        }
+    }
+    /// The number of bytes between the start of each row
+    pub fn line_stride(&self) -> usize {
+        self.line_stride
+    }
+    pub fn format(&self) -> SendColorFormat {
+        self.format
+    }
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.len) }
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.blocks.as_mut_ptr() as *mut u8, self.len) }
+    }
+}
+
+/// The data of a video frame to send
+enum SendVideoData {
+    Vec(Vec<u8>),
+    Aligned(SendVideoBuffer),
+}
 
 pub struct NDISendVideoFrameBuilder {
     instance: sdk::NDIlib_video_frame_v2_t,
     metadata: Option<String>,
-    data: Vec<u8>,
+    data: SendVideoData,
+    format: SendColorFormat,
 }
 impl NDISendVideoFrameBuilder {
     pub fn with_framerate(mut self, num: i32, den: i32) -> Self {
@@ -84,14 +248,30 @@ impl NDISendVideoFrameBuilder {
         self.instance.picture_aspect_ratio = aspect_ratio;
         self
     }
+    /// The timecode of the frame, in 100ns units. Defaults to `TIMECODE_SYNTHESIZE`
     pub fn with_timecode(mut self, timecode: i64) -> Self {
         self.instance.timecode = timecode;
         self
     }
+    /// Let the SDK generate the timecode, undoing any previous `with_timecode`
+    pub fn with_synthesized_timecode(self) -> Self {
+        self.with_timecode(TIMECODE_SYNTHESIZE)
+    }
     pub fn with_data(mut self, data: Vec<u8>, line_stride: i32, format: SendColorFormat) -> Self {
-        self.data = data;
+        self.data = SendVideoData::Vec(data);
         self.instance.line_stride_in_bytes = line_stride;
         self.instance.FourCC = format as u32;
+        self.format = format;
+        self
+    }
+    /// Use an aligned buffer from `SendVideoBuffer::alloc` for the data, which avoids the SDK copying unaligned data
+    ///
+    /// The buffer must be the same size as the frame, or `build()` will fail
+    pub fn with_buffer(mut self, buffer: SendVideoBuffer) -> Self {
+        self.instance.line_stride_in_bytes = buffer.line_stride as i32;
+        self.instance.FourCC = buffer.format as u32;
+        self.format = buffer.format;
+        self.data = SendVideoData::Aligned(buffer);
         self
     }
     pub fn with_metadata(mut self, metadata: String) -> Self {
@@ -106,19 +286,36 @@ impl NDISendVideoFrameBuilder {
         // TODO - change return error type
         let mut res = NDISendVideoFrame {
             instance: self.instance,
-            metadata: self.metadata,
+            metadata: None,
             data: self.data,
         };
 
-        if let Some(metadata) = &res.metadata {
-            res.instance.p_metadata = CString::new(metadata.as_bytes())
-                .map_err(|_| SendCreateError::InvalidName)?
-                .as_ptr();
+        if let Some(metadata) = &self.metadata {
+            let metadata = CString::new(metadata.as_bytes()).map_err(|_| SendCreateError::InvalidName)?;
+            res.instance.p_metadata = metadata.as_ptr();
+            res.metadata = Some(metadata);
         }
 
-        res.data
-            .resize((res.instance.line_stride_in_bytes * res.instance.yres) as usize, 0);
-        res.instance.p_data = res.data.as_mut_ptr();
+        res.instance.p_data = match &mut res.data {
+            SendVideoData::Vec(data) => {
+                // Pad the data to the full size of the frame, including any chroma planes, so the SDK can't read past
+                // the end of it
+                let len = frame_data_len(
+                    self.format.four_cc(),
+                    res.instance.xres.max(0) as usize,
+                    res.instance.yres.max(0) as usize,
+                    res.instance.line_stride_in_bytes.max(0) as usize,
+                );
+                data.resize(len, 0);
+                data.as_mut_ptr()
+            }
+            SendVideoData::Aligned(buffer) => {
+                if buffer.width != res.instance.xres || buffer.height != res.instance.yres {
+                    return Err(SendCreateError::InvalidFormat);
+                }
+                buffer.as_mut_slice().as_mut_ptr()
+            }
+        };
 
         Ok(res)
     }
@@ -130,16 +327,28 @@ pub struct NDISendAudioFrameBuilder {
 }
 
 impl NDISendAudioFrameBuilder {
+    /// The timecode of the audio, in 100ns units. Defaults to `TIMECODE_SYNTHESIZE`
     pub fn with_timecode(mut self, timecode: i64) -> Self {
         self.instance.timecode = timecode;
         self
     }
+    /// Let the SDK generate the timecode, undoing any previous `with_timecode`
+    pub fn with_synthesized_timecode(self) -> Self {
+        self.with_timecode(TIMECODE_SYNTHESIZE)
+    }
     pub fn with_data(mut self, data: Vec<f32>, sample_count: i32) -> Self {
         self.data = data;
         self.instance.no_samples = sample_count;
-        self.instance.channel_stride_in_bytes = (self.instance.no_samples)*4;
+        self.instance.channel_stride_in_bytes = (self.instance.no_samples) * 4;
         self
     }
+    /// Set the audio data from interleaved samples, as used by most audio libraries
+    pub fn with_interleaved_data(self, data: &[f32]) -> Self {
+        let channel_count = self.instance.no_channels.max(0) as usize;
+        let planar = deinterleave_audio(data, channel_count);
+        let sample_count = planar.len().checked_div(channel_count).unwrap_or(0);
+        self.with_data(planar, sample_count as i32)
+    }
     pub fn with_timestamp(mut self, timestamp: i64) -> Self {
         self.instance.timestamp = timestamp;
         self
@@ -164,14 +373,15 @@ pub fn create_ndi_send_video_frame(width: i32, height: i32, frame_type: FrameFor
             frame_rate_D: 0,
             picture_aspect_ratio: 0.0,
             frame_format_type: frame_type as u32,
-            timecode: sdk::NDIlib_send_timecode_synthesize,
+            timecode: TIMECODE_SYNTHESIZE,
             p_data: null_mut(),
             line_stride_in_bytes: 0,
             p_metadata: null(),
             timestamp: 0,
         },
         metadata: None,
-        data: vec![],
+        data: SendVideoData::Vec(vec![]),
+        format: SendColorFormat::Bgra,
     }
 }
 
@@ -181,7 +391,7 @@ pub fn create_ndi_send_audio_frame(channel_count: i32, sample_rate: i32) -> NDIS
             sample_rate,
             no_channels: channel_count,
             no_samples: 0,
-            timecode: sdk::NDIlib_send_timecode_synthesize,
+            timecode: TIMECODE_SYNTHESIZE,
             channel_stride_in_bytes: 0,
             p_data: null_mut(),
             p_metadata: null(),
@@ -193,8 +403,8 @@ pub fn create_ndi_send_audio_frame(channel_count: i32, sample_rate: i32) -> NDIS
 
 pub struct NDISendVideoFrame {
     instance: sdk::NDIlib_video_frame_v2_t,
-    metadata: Option<String>,
-    data: Vec<u8>,
+    metadata: Option<CString>,
+    data: SendVideoData,
 }
 
 pub struct NDISendAudioFrame {
@@ -205,33 +415,147 @@ pub struct NDISendAudioFrame {
 #[derive(Debug)]
 pub enum SendCreateError {
     InvalidName,
+    InvalidGroups,
+    /// The video size or frame rate is not valid
+    InvalidFormat,
     Failed,
 }
 
+/// Options for creating a SendInstance
+pub struct SendInstanceBuilder {
+    sdk_instance: Arc<NDIHandle>,
+    name: String,
+    groups: Option<String>,
+    clock_video: bool,
+    clock_audio: bool,
+}
+impl SendInstanceBuilder {
+    /// Advertise the source in the given comma separated list of groups, instead of the default group
+    pub fn with_groups(mut self, groups: String) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+    /// Whether `send_video` should block so that frames go out at their frame rate. Defaults to true
+    ///
+    /// Leave this on for anything generated in real time, such as a test pattern, or frames are sent as quickly as
+    /// they are produced and flood the network. Turn it off when the frames are already paced, eg by a capture card,
+    /// or to send from a file as fast as possible.
+    pub fn with_clock_video(mut self, clock_video: bool) -> Self {
+        self.clock_video = clock_video;
+        self
+    }
+    /// Whether `send_audio` should block so that audio goes out at its sample rate. Defaults to false
+    ///
+    /// When sending both audio and video from one thread, only one of them should be clocked, or each will wait on
+    /// the other and the output will stutter.
+    pub fn with_clock_audio(mut self, clock_audio: bool) -> Self {
+        self.clock_audio = clock_audio;
+        self
+    }
+    pub fn build(self) -> Result<SendInstance, SendCreateError> {
+        let name = CString::new(self.name.as_bytes()).map_err(|_| SendCreateError::InvalidName)?;
+        let groups = match self.groups {
+            None => None,
+            Some(groups) => Some(CString::new(groups.as_bytes()).map_err(|_| SendCreateError::InvalidGroups)?),
+        };
+
+        let props = sdk::NDIlib_send_create_t {
+            p_ndi_name: name.as_ptr(),
+            p_groups: groups.as_ref().map_or(null(), |g| g.as_ptr()),
+            clock_video: self.clock_video,
+            clock_audio: self.clock_audio,
+        };
+
+        let instance = unsafe { self.sdk_instance.NDIlib_send_create.unwrap()(&props) };
+
+        if instance.is_null() {
+            Err(SendCreateError::Failed)
+        } else {
+            Ok(SendInstance {
+                sdk_instance: self.sdk_instance,
+                instance,
+                in_flight_video: None,
+            })
+        }
+    }
+}
+
+pub fn create_send_builder(sdk_instance: Arc<NDIHandle>, name: String) -> SendInstanceBuilder {
+    SendInstanceBuilder {
+        sdk_instance,
+        name,
+        groups: None,
+        clock_video: true,
+        clock_audio: false,
+    }
+}
+
 pub fn create_send_instance(
     sdk_instance: Arc<NDIHandle>,
     name: String,
+    groups: Option<String>,
     clock_video: bool,
     clock_audio: bool,
 ) -> Result<SendInstance, SendCreateError> {
-    let name2 = CString::new(name.as_bytes()).map_err(|_| SendCreateError::InvalidName)?;
-
-    let props = sdk::NDIlib_send_create_t {
-        p_ndi_name: name2.as_ptr(),
-        p_groups: null(),
-        clock_video,
-        clock_audio,
-    };
-
-    let instance = unsafe { sdk_instance.NDIlib_send_create.unwrap()(&props) };
-
-    if instance.is_null() {
-        Err(SendCreateError::Failed)
-    } else {
-        Ok(SendInstance {
-            sdk_instance,
-            instance,
-            in_flight_video: None,
-        })
+    let mut builder = create_send_builder(sdk_instance, name)
+        .with_clock_video(clock_video)
+        .with_clock_audio(clock_audio);
+    if let Some(groups) = groups {
+        builder = builder.with_groups(groups);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_len(frame: &NDISendVideoFrame) -> usize {
+        match &frame.data {
+            SendVideoData::Vec(data) => data.len(),
+            SendVideoData::Aligned(buffer) => buffer.as_slice().len(),
+        }
+    }
+
+    #[test]
+    fn vec_data_is_padded_to_include_chroma() {
+        let frame = create_ndi_send_video_frame(4, 2, FrameFormatType::Progressive)
+            .with_data(vec![0; 8], 4, SendColorFormat::I420)
+            .build()
+            .unwrap();
+        // 8 bytes of Y, then 2 bytes each of U and V
+        assert_eq!(data_len(&frame), 12);
+
+        let frame = create_ndi_send_video_frame(4, 2, FrameFormatType::Progressive)
+            .with_data(vec![], 4, SendColorFormat::Nv12)
+            .build()
+            .unwrap();
+        assert_eq!(data_len(&frame), 12);
+    }
+
+    #[test]
+    fn buffers_fit_their_format() {
+        let formats = [
+            SendColorFormat::Uyvy,
+            SendColorFormat::Yv12,
+            SendColorFormat::Nv12,
+            SendColorFormat::I420,
+            SendColorFormat::Bgra,
+            SendColorFormat::Bgrx,
+            SendColorFormat::Rgba,
+            SendColorFormat::Rgbx,
+            SendColorFormat::Uyva,
+        ];
+        for format in formats.iter() {
+            let buffer = SendVideoBuffer::alloc(33, 5, *format);
+            let four_cc = format.four_cc();
+            assert_eq!(four_cc as u32, *format as u32);
+            assert!(buffer.line_stride() >= min_stride(four_cc, 33));
+            assert_eq!(
+                buffer.as_slice().len(),
+                frame_data_len(four_cc, 33, 5, buffer.line_stride())
+            );
+            assert_eq!(buffer.as_slice().as_ptr() as usize % 16, 0);
+        }
     }
 }
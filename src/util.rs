@@ -1,6 +1,8 @@
 use crate::finder::FindSource;
+use crate::receive::MetadataFrame;
 use crate::sdk;
-use std::ffi::{CString, NulError};
+use std::convert::TryInto;
+use std::ffi::{CStr, CString, NulError};
 use std::ptr::null;
 
 // Messy return type to keep the CStrings alive long enough
@@ -24,3 +26,163 @@ pub fn to_ndi_source(source: &FindSource) -> Result<(CString, Option<CString>, s
 
     Ok((source_name, source_url, res))
 }
+
+// Messy return type to keep the CString alive long enough
+pub fn to_ndi_metadata(frame: &MetadataFrame) -> Result<(CString, sdk::NDIlib_metadata_frame_t), NulError> {
+    let data = CString::new(frame.xml.as_bytes())?;
+
+    let res = sdk::NDIlib_metadata_frame_t {
+        length: data.as_bytes_with_nul().len() as i32,
+        timecode: frame.timecode,
+        p_data: data.as_ptr() as *mut _,
+    };
+
+    Ok((data, res))
+}
+
+pub fn from_ndi_metadata(frame: &sdk::NDIlib_metadata_frame_t) -> MetadataFrame {
+    let xml = if frame.p_data.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(frame.p_data).to_string_lossy().into_owned() }
+    };
+
+    MetadataFrame {
+        xml,
+        timecode: frame.timecode,
+    }
+}
+
+/// Convert planar audio, where each channel starts `channel_stride` samples after the previous, into interleaved audio
+pub fn interleave_audio(planar: &[f32], channel_count: usize, sample_count: usize, channel_stride: usize) -> Vec<f32> {
+    let mut res = Vec::with_capacity(channel_count * sample_count);
+    for sample in 0..sample_count {
+        for channel in 0..channel_count {
+            res.push(planar[channel * channel_stride + sample]);
+        }
+    }
+    res
+}
+
+/// Convert interleaved audio into tightly packed planar audio. Any trailing partial sample is discarded
+pub fn deinterleave_audio(interleaved: &[f32], channel_count: usize) -> Vec<f32> {
+    if channel_count == 0 {
+        return vec![];
+    }
+
+    let sample_count = interleaved.len() / channel_count;
+    let mut res = Vec::with_capacity(channel_count * sample_count);
+    for channel in 0..channel_count {
+        for sample in 0..sample_count {
+            res.push(interleaved[sample * channel_count + channel]);
+        }
+    }
+    res
+}
+
+/// A fast non cryptographic hash of some data, using FNV-1a over 8 bytes at a time
+///
+/// This is only for telling whether two buffers differ, so is not resistant to crafted collisions. It is stable
+/// across runs and platforms, unlike the std hasher.
+pub fn hash_bytes(data: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET ^ data.len() as u64;
+    let words = data.chunks_exact(8);
+    let tail = words.remainder();
+    for word in words {
+        let word = u64::from_le_bytes(word.try_into().unwrap());
+        hash = (hash ^ word).wrapping_mul(PRIME);
+    }
+    for byte in tail {
+        hash = (hash ^ *byte as u64).wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Find the attributes of the first element with the given tag in an XML string, eg `<ndi_product a="1"/>`
+///
+/// This is only meant for the simple single element messages NDI sends, so doesn't handle comments or CDATA
+pub fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}", tag);
+    let mut rest = xml;
+    loop {
+        let start = rest.find(&open)? + open.len();
+        rest = &rest[start..];
+        // Make sure this isn't a longer tag with the same prefix
+        if rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            let end = rest.find('>')?;
+            return Some(rest[..end].trim_end_matches('/'));
+        }
+    }
+}
+
+/// Get the value of an attribute from the attributes of an element, unescaping any entities
+pub fn xml_attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return Some(unescape_xml(&value[1..end]));
+        }
+        rest = &value[end + 1..];
+    }
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mono() {
+        let samples = [0.1, 0.2, 0.3];
+        assert_eq!(interleave_audio(&samples, 1, 3, 3), samples);
+        assert_eq!(deinterleave_audio(&samples, 1), samples);
+    }
+
+    #[test]
+    fn six_channels() {
+        // Two samples per channel, with the channel number in the tens
+        let planar = [10.0, 11.0, 20.0, 21.0, 30.0, 31.0, 40.0, 41.0, 50.0, 51.0, 60.0, 61.0];
+        let interleaved = [10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 11.0, 21.0, 31.0, 41.0, 51.0, 61.0];
+        assert_eq!(interleave_audio(&planar, 6, 2, 2), interleaved);
+        assert_eq!(deinterleave_audio(&interleaved, 6), planar);
+    }
+
+    #[test]
+    fn round_trip() {
+        let planar: Vec<f32> = (0..48).map(|i| i as f32 / 48.0).collect();
+        let interleaved = interleave_audio(&planar, 4, 12, 12);
+        assert_eq!(deinterleave_audio(&interleaved, 4), planar);
+    }
+
+    #[test]
+    fn padded_channel_stride() {
+        // Each channel has one padding sample after its two samples
+        let planar = [1.0, 2.0, -1.0, 3.0, 4.0, -1.0];
+        let interleaved = interleave_audio(&planar, 2, 2, 3);
+        assert_eq!(interleaved, [1.0, 3.0, 2.0, 4.0]);
+        assert_eq!(deinterleave_audio(&interleaved, 2), [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn partial_and_empty() {
+        assert_eq!(deinterleave_audio(&[1.0, 2.0, 3.0], 2), [1.0, 2.0]);
+        assert!(deinterleave_audio(&[1.0, 2.0], 0).is_empty());
+        assert!(interleave_audio(&[], 2, 0, 0).is_empty());
+    }
+}
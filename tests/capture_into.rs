@@ -0,0 +1,69 @@
+mod common;
+
+use ndi_sdk::receive::{ReceiveColorFormat, ReceiveInstanceExt};
+use std::time::Instant;
+
+/// Capture into a ring of preallocated buffers, as a render thread would, using each buffer in turn
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn capture_into_ring() {
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 36;
+    let instance = common::load();
+    let name = common::unique_name("capture into");
+    let _pattern = instance
+        .create_test_pattern(name.clone(), WIDTH as i32, HEIGHT as i32, 30)
+        .unwrap();
+
+    let receiver = instance
+        .create_receive_builder()
+        .with_color_format(ReceiveColorFormat::BgrxBgra)
+        .build()
+        .unwrap();
+    receiver
+        .connect_by_name(&name, common::TIMEOUT.as_millis() as u32)
+        .expect("The test pattern was not found");
+
+    // Allow for padded rows
+    let mut ring = vec![vec![0u8; WIDTH * HEIGHT * 8]; 3];
+    let mut infos = Vec::new();
+    let mut next = 0;
+    let deadline = Instant::now() + common::TIMEOUT;
+    while infos.len() < ring.len() * 2 && Instant::now() < deadline {
+        if let Some(info) = receiver.capture_video_into(1000, &mut ring[next]).unwrap() {
+            infos.push((next, info));
+            next = (next + 1) % ring.len();
+        }
+    }
+    assert_eq!(infos.len(), ring.len() * 2, "Not enough frames were received");
+
+    for (buffer, info) in &infos {
+        assert_eq!((info.format.width, info.format.height), (WIDTH as i32, HEIGHT as i32));
+        assert!(info.line_stride >= WIDTH * 4);
+        assert_eq!(info.data_len, info.line_stride * HEIGHT);
+        // The first pixel of every frame is in the white bar, unless the moving line is over it, which is also white
+        let data = &ring[*buffer][..info.data_len];
+        assert!(
+            common::close_to(&data[..3], &[191, 191, 191], 16) || common::close_to(&data[..3], &[255, 255, 255], 16)
+        );
+    }
+
+    // Every buffer in the ring was used
+    for (i, buffer) in ring.iter().enumerate() {
+        assert!(infos.iter().any(|(b, _)| *b == i));
+        assert!(buffer.iter().any(|&b| b != 0));
+    }
+
+    // A buffer which is too small is reported with the size needed
+    let deadline = Instant::now() + common::TIMEOUT;
+    loop {
+        match receiver.capture_video_into(1000, &mut [0; 16]) {
+            Ok(None) => assert!(Instant::now() < deadline, "No more frames were received"),
+            Err(ndi_sdk::NdiError::BufferTooSmall(len)) => {
+                assert_eq!(len, infos[0].1.data_len);
+                break;
+            }
+            res => panic!("Expected BufferTooSmall, got {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use ndi_sdk::receive::{FourCCType, ReceiveColorFormat, ReceiveInstanceExt, VideoFrame};
+use ndi_sdk::send::{create_ndi_send_video_frame, FrameFormatType, SendColorFormat};
+use std::time::Instant;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 36;
+
+/// 75% blue, in each byte order
+const BLUE_BGR: [u8; 3] = [191, 0, 0];
+const BLUE_RGB: [u8; 3] = [0, 0, 191];
+/// The same blue as U, Y0, V, Y1, between its BT.601 and BT.709 values
+const BLUE_UYVY: [u8; 4] = [212, 32, 117, 32];
+/// The alpha of the frames sent with alpha
+const ALPHA: u8 = 128;
+
+/// The pixel in the middle of a received frame
+struct Received {
+    four_cc: FourCCType,
+    /// The 4 bytes of the pixel as received, or of its pair for UYVY
+    pixel: Vec<u8>,
+    /// The pixel converted to RGBA
+    rgba: Vec<u8>,
+}
+
+fn centre_pixel(video: &VideoFrame) -> Received {
+    let data = video.lock_data().unwrap();
+    let row = &data[HEIGHT / 2 * video.line_stride()..];
+    let x = WIDTH / 2;
+    let pixel = match video.four_cc() {
+        FourCCType::UYVY => row[x / 2 * 4..x / 2 * 4 + 4].to_vec(),
+        _ => row[x * 4..x * 4 + 4].to_vec(),
+    };
+    drop(data);
+
+    let i = (HEIGHT / 2 * WIDTH + x) * 4;
+    let rgba = video.to_rgba().expect("The frame could not be converted")[i..i + 4].to_vec();
+    Received {
+        four_cc: video.four_cc(),
+        pixel,
+        rgba,
+    }
+}
+
+/// Send a solid blue frame, as BGRA with an alpha of `ALPHA` or as BGRX, and receive it in a color format
+fn receive_blue(color_format: ReceiveColorFormat, alpha: bool) -> Received {
+    let instance = common::load();
+    let name = common::unique_name(&format!("{:?} alpha {}", color_format, alpha));
+    let mut sender = instance.create_send_instance(name.clone(), None, true, false).unwrap();
+
+    let receiver = instance
+        .create_receive_builder()
+        .with_color_format(color_format)
+        .build()
+        .unwrap();
+    receiver
+        .connect_by_name(&name, common::TIMEOUT.as_millis() as u32)
+        .expect("The sender was not found");
+
+    let (format, a) = if alpha {
+        (SendColorFormat::Bgra, ALPHA)
+    } else {
+        (SendColorFormat::Bgrx, 255)
+    };
+    let data = [BLUE_BGR[0], BLUE_BGR[1], BLUE_BGR[2], a].repeat(WIDTH * HEIGHT);
+
+    // The sender is clocked, so this sends at the frame rate until the receiver has a frame
+    let deadline = Instant::now() + common::TIMEOUT;
+    while Instant::now() < deadline {
+        let frame = create_ndi_send_video_frame(WIDTH as i32, HEIGHT as i32, FrameFormatType::Progressive)
+            .with_framerate(30, 1)
+            .with_data(data.clone(), WIDTH as i32 * 4, format)
+            .build()
+            .unwrap();
+        sender.send_video(frame);
+
+        if let Ok(Some(video)) = receiver.capture_video(0) {
+            return centre_pixel(&video);
+        }
+    }
+    panic!("No video was received");
+}
+
+/// Check a received pixel, where `expected` holds the color channels and then the alpha if there is one
+fn assert_pixel(received: &Received, four_cc: FourCCType, expected: &[u8]) {
+    assert_eq!(received.four_cc, four_cc);
+    assert!(
+        common::close_to(&received.pixel[..expected.len()], expected, 16),
+        "Expected {:?}, got {:?}",
+        expected,
+        received.pixel
+    );
+}
+
+fn with_alpha(color: [u8; 3]) -> [u8; 4] {
+    [color[0], color[1], color[2], ALPHA]
+}
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn bgrx_bgra() {
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::BgrxBgra, false),
+        FourCCType::BGRX,
+        &BLUE_BGR,
+    );
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::BgrxBgra, true),
+        FourCCType::BGRA,
+        &with_alpha(BLUE_BGR),
+    );
+}
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn rgbx_rgba() {
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::RgbxRgba, false),
+        FourCCType::RGBX,
+        &BLUE_RGB,
+    );
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::RgbxRgba, true),
+        FourCCType::RGBA,
+        &with_alpha(BLUE_RGB),
+    );
+}
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn uyvy_bgra() {
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::UyvyBgra, false),
+        FourCCType::UYVY,
+        &BLUE_UYVY,
+    );
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::UyvyBgra, true),
+        FourCCType::BGRA,
+        &with_alpha(BLUE_BGR),
+    );
+}
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn uyvy_rgba() {
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::UyvyRgba, false),
+        FourCCType::UYVY,
+        &BLUE_UYVY,
+    );
+    assert_pixel(
+        &receive_blue(ReceiveColorFormat::UyvyRgba, true),
+        FourCCType::RGBA,
+        &with_alpha(BLUE_RGB),
+    );
+}
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn fastest() {
+    // Any format may be chosen, so check the color once converted, and the alpha if the format has one
+    for &alpha in &[false, true] {
+        let received = receive_blue(ReceiveColorFormat::Fastest, alpha);
+        let has_alpha = matches!(received.four_cc, FourCCType::UYVA | FourCCType::BGRA | FourCCType::RGBA);
+        let expected: &[u8] = if alpha && has_alpha {
+            &[0, 0, 191, ALPHA]
+        } else {
+            &BLUE_RGB
+        };
+        assert!(
+            common::close_to(&received.rgba[..expected.len()], expected, 16),
+            "Expected {:?} from {:?}, got {:?}",
+            expected,
+            received.four_cc,
+            received.rgba
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Helpers for the tests which need the NDI runtime
+//!
+//! These tests are ignored by default, so run them with `cargo test -- --ignored` on a machine with the runtime
+//! installed. They send and receive on the local machine, so need no other sources on the network.
+
+// Each test file only uses some of the helpers
+#![allow(dead_code)]
+
+use ndi_sdk::receive::{ReceiveInstance, ReceiveInstanceExt, VideoFrame};
+use ndi_sdk::NDIInstance;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a source to be found or a frame to arrive
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn load() -> NDIInstance {
+    #[cfg(not(feature = "dynamic-link"))]
+    let instance = ndi_sdk::load();
+    #[cfg(feature = "dynamic-link")]
+    let instance = ndi_sdk::load(None);
+    instance.expect("Failed to load the NDI runtime")
+}
+
+/// A source name unique to this process and test, so tests running at the same time don't see each other's sources
+pub fn unique_name(test: &str) -> String {
+    format!("ndi-sdk test {} {}", std::process::id(), test)
+}
+
+/// Wait for the receiver to get a video frame
+pub fn wait_for_video(receiver: &Arc<ReceiveInstance>) -> Option<VideoFrame> {
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(Some(video)) = receiver.capture_video(1000) {
+            return Some(video);
+        }
+    }
+    None
+}
+
+/// Whether each channel of two pixels is within `tolerance`, as NDI compresses video so values aren't exact
+pub fn close_to(actual: &[u8], expected: &[u8], tolerance: u8) -> bool {
+    actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| a.abs_diff(*e) <= tolerance)
+}
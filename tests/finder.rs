@@ -0,0 +1,38 @@
+mod common;
+
+/// A source has to stay valid after the SDK frees the list it was read from
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn sources_outlive_the_list() {
+    let instance = common::load();
+    let name = common::unique_name("finder");
+    let _pattern = instance.create_test_pattern(name.clone(), 64, 36, 30).unwrap();
+
+    let finder = instance
+        .create_find_builder()
+        .with_show_local_sources(true)
+        .build()
+        .unwrap();
+    let timeout = common::TIMEOUT.as_millis() as u32;
+    let source = finder
+        .wait_for_source(timeout, |s| s.name.contains(&name))
+        .expect("The test pattern was not found");
+    let clone = source.clone();
+    let url = source.url.clone();
+    assert!(url.is_some());
+
+    // Change the list so the SDK reallocates it, then read it again
+    let other_name = common::unique_name("finder other");
+    let _other = instance.create_test_pattern(other_name.clone(), 64, 36, 30).unwrap();
+    finder
+        .wait_for_source(timeout, |s| s.name.contains(&other_name))
+        .expect("The second test pattern was not found");
+    let sources = finder.get_current_sources();
+    assert!(sources.contains(&source));
+
+    drop(sources);
+    drop(finder);
+    assert_eq!(clone.name, source.name);
+    assert!(clone.name.contains(&name));
+    assert_eq!(clone.url, url);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use ndi_sdk::receive::ReceiveColorFormat;
+use std::thread;
+
+/// Receivers created from one instance can capture on their own threads at the same time
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn receivers_on_threads() {
+    const RECEIVERS: usize = 3;
+    let instance = common::load();
+
+    let names: Vec<_> = (0..RECEIVERS)
+        .map(|i| common::unique_name(&format!("multi receive {}", i)))
+        .collect();
+    let _patterns: Vec<_> = names
+        .iter()
+        .map(|name| instance.create_test_pattern(name.clone(), 64, 36, 30).unwrap())
+        .collect();
+
+    let threads: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let receiver = instance
+                .create_receive_builder()
+                .with_color_format(ReceiveColorFormat::Fastest)
+                .build()
+                .unwrap();
+
+            thread::spawn(move || {
+                receiver
+                    .connect_by_name(&name, common::TIMEOUT.as_millis() as u32)
+                    .expect("The test pattern was not found");
+                let video = common::wait_for_video(&receiver).expect("No video was received");
+                (video.width, video.height)
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().expect("Receiver thread panicked"), (64, 36));
+    }
+}
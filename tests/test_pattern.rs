@@ -0,0 +1,38 @@
+mod common;
+
+use ndi_sdk::receive::{FourCCType, ReceiveColorFormat};
+
+#[test]
+#[ignore = "needs the NDI runtime"]
+fn test_pattern_round_trip() {
+    let instance = common::load();
+    let name = common::unique_name("round trip");
+    let pattern = instance.create_test_pattern(name.clone(), 64, 36, 30).unwrap();
+    assert!(pattern.is_running());
+
+    let receiver = instance
+        .create_receive_builder()
+        .with_color_format(ReceiveColorFormat::BgrxBgra)
+        .build()
+        .unwrap();
+    receiver
+        .connect_by_name(&name, 10_000)
+        .expect("The test pattern was not found");
+
+    let video = common::wait_for_video(&receiver).expect("No video was received");
+    assert_eq!((video.width, video.height), (64, 36));
+    assert_eq!(video.four_cc(), FourCCType::BGRX);
+
+    // The last two columns are in the blue bar, and the moving line can only cover one of them
+    let data = video.lock_data().unwrap();
+    let blue = [191, 0, 0];
+    assert!(
+        common::close_to(&data[62 * 4..62 * 4 + 3], &blue, 16)
+            || common::close_to(&data[63 * 4..63 * 4 + 3], &blue, 16),
+        "Expected blue, got {:?}",
+        &data[62 * 4..64 * 4]
+    );
+    drop(data);
+
+    assert!(pattern.is_running());
+}